@@ -0,0 +1,618 @@
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::mp4;
+use crate::utils::{read_at, readable_duration};
+
+// Tags and headers bigger than this are cut off, large covers make up most of them
+const MAX_HEADER_SIZE: u64 = 16 * 1024 * 1024;
+
+// How far after the tags the first MPEG frame and the last Ogg page are looked for
+const SEARCH_SIZE: u64 = 64 * 1024;
+
+// What the preview and the list columns show of a song
+#[derive(Debug, Clone, Default)]
+pub struct Tags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub track: Option<u32>,
+    pub track_total: Option<u32>,
+    pub duration: Option<Duration>,
+    // In kbit/s
+    pub bitrate: Option<u32>,
+    // Encoded image as it's stored in the file, only read for the preview
+    pub cover: Option<Vec<u8>>,
+}
+
+impl Tags {
+    // "3/12", or only the number if the total is unknown
+    pub fn track_label(&self) -> Option<String> {
+        match (self.track, self.track_total) {
+            (Some(track), Some(total)) => Some(format!("{}/{}", track, total)),
+            (track, _) => track.map(|t| t.to_string()),
+        }
+    }
+
+    // Label and value of every known field, in the order they're shown
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        [
+            ("Title", self.title.clone()),
+            ("Artist", self.artist.clone()),
+            ("Album", self.album.clone()),
+            ("Track", self.track_label()),
+            ("Duration", self.duration.map(|d| readable_duration(Some(d)))),
+            ("Bitrate", self.bitrate.map(|b| format!("{} kbit/s", b))),
+        ]
+            .into_iter()
+            .filter_map(|(label, value)| value.map(|v| (label, v)))
+            .collect()
+    }
+
+    // Fills in what `other` knows and `self` doesn't
+    fn or(self, other: Tags) -> Tags {
+        Tags {
+            title: self.title.or(other.title),
+            artist: self.artist.or(other.artist),
+            album: self.album.or(other.album),
+            track: self.track.or(other.track),
+            track_total: self.track_total.or(other.track_total),
+            duration: self.duration.or(other.duration),
+            bitrate: self.bitrate.or(other.bitrate),
+            cover: self.cover.or(other.cover),
+        }
+    }
+}
+
+// Reads the tags by the file's extension, the cover is skipped unless asked for.
+// Only the start and end of the file are read, not the audio in between
+pub fn read(path: &Path, cover: bool) -> Option<Tags> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let tail = |file: &mut File, size: u64| read_at(file, len.saturating_sub(size), size);
+    let mut tags = match extension.as_str() {
+        "mp3" => mp3(&mp3_head(&mut file)?, &tail(&mut file, 128)?, len),
+        "flac" => flac(&flac_head(&mut file)?, len),
+        "ogg" | "oga" | "opus" => ogg(&ogg_head(&mut file)?, &tail(&mut file, SEARCH_SIZE)?, len),
+        "m4a" | "m4b" | "mp4" | "aac" => m4a(&mp4::read_box(&mut file, b"moov")?, len),
+        _ => None,
+    }?;
+    if !cover {
+        tags.cover = None;
+    }
+    Some(tags)
+}
+
+// The ID3v2 tag if there is one and enough after it to find the first frame
+fn mp3_head<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    let header = read_at(reader, 0, 10)?;
+    let tag = match header.get(6..10) {
+        Some(size) if header.starts_with(b"ID3") => 20 + synchsafe(size) as u64,
+        _ => 0,
+    };
+    read_at(reader, 0, tag.min(MAX_HEADER_SIZE) + SEARCH_SIZE)
+}
+
+// `head` is the start of the file, `tail` its last 128 bytes and `len` its size
+fn mp3(head: &[u8], tail: &[u8], len: u64) -> Option<Tags> {
+    let (id3, audio_start) = match id3v2(head) {
+        Some((tags, len)) => (tags, len),
+        None => (Tags::default(), 0),
+    };
+    let v1 = id3v1(tail);
+    let audio_end = if v1.is_some() { len.saturating_sub(128) } else { len };
+    let audio_bytes = audio_end.saturating_sub(audio_start as u64);
+    let stream = head.get(audio_start..).and_then(|audio| mpeg(audio, audio_bytes)).unwrap_or_default();
+    Some(id3.or(v1.unwrap_or_default()).or(stream))
+}
+
+// ID3v2.2 to 2.4 at the start of the file, with the length of the tag
+fn id3v2(data: &[u8]) -> Option<(Tags, usize)> {
+    if data.get(0..3)? != b"ID3" {
+        return None;
+    }
+    let version = *data.get(3)?;
+    let flags = *data.get(5)?;
+    let size = synchsafe(data.get(6..10)?);
+    let footer = if flags & 0x10 != 0 { 10 } else { 0 };
+    let end = (10 + size).min(data.len());
+    let mut frames = &data[10..end];
+
+    if flags & 0x40 != 0 && version >= 3 {
+        let extended = if version == 4 { synchsafe(frames.get(0..4)?) } else { mp4::u32_at(frames, 0)? as usize + 4 };
+        frames = frames.get(extended..)?;
+    }
+
+    let mut tags = Tags::default();
+    let (id_len, header_len) = if version == 2 { (3, 6) } else { (4, 10) };
+    while frames.len() >= header_len && frames[0] != 0 {
+        let id = &frames[..id_len];
+        let size = match version {
+            2 => u32::from_be_bytes([0, frames[3], frames[4], frames[5]]) as usize,
+            4 => synchsafe(&frames[4..8]),
+            _ => mp4::u32_at(frames, 4)? as usize,
+        };
+        let Some(body) = frames.get(header_len..header_len + size) else { break };
+        match id {
+            b"TIT2" | b"TT2" => tags.title = id3_text(body),
+            b"TPE1" | b"TP1" => tags.artist = id3_text(body),
+            b"TALB" | b"TAL" => tags.album = id3_text(body),
+            b"TRCK" | b"TRK" => {
+                if let Some(track) = id3_text(body) {
+                    (tags.track, tags.track_total) = track_numbers(&track);
+                }
+            }
+            // In milliseconds, rarely present but exact when it is
+            b"TLEN" | b"TLE" => {
+                tags.duration = id3_text(body).and_then(|ms| ms.parse().ok()).map(Duration::from_millis);
+            }
+            b"APIC" if tags.cover.is_none() => tags.cover = apic(body),
+            b"PIC" if tags.cover.is_none() => tags.cover = pic(body),
+            _ => {}
+        }
+        frames = &frames[header_len + size..];
+    }
+    Some((tags, end + footer))
+}
+
+// Sizes in ID3v2 headers use 7 bits per byte
+fn synchsafe(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |size, b| (size << 7) | (*b as usize & 0x7f))
+}
+
+fn id3_text(body: &[u8]) -> Option<String> {
+    let (encoding, text) = body.split_first()?;
+    let text = decode(*encoding, text);
+    // Several values are separated by NUL, the first one is enough
+    let text = text.split('\0').next().unwrap_or("").trim().to_owned();
+    (!text.is_empty()).then_some(text)
+}
+
+fn decode(encoding: u8, bytes: &[u8]) -> String {
+    match encoding {
+        1 | 2 => {
+            let big_endian = encoding == 2 || bytes.starts_with(&[0xfe, 0xff]);
+            let bytes = if bytes.starts_with(&[0xfe, 0xff]) || bytes.starts_with(&[0xff, 0xfe]) { &bytes[2..] } else { bytes };
+            let units = bytes.chunks_exact(2).map(|pair| {
+                if big_endian { u16::from_be_bytes([pair[0], pair[1]]) } else { u16::from_le_bytes([pair[0], pair[1]]) }
+            });
+            char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+        }
+        3 => String::from_utf8_lossy(bytes).to_string(),
+        // Latin-1 maps straight to the first 256 code points
+        _ => bytes.iter().map(|b| *b as char).collect(),
+    }
+}
+
+// Skips a NUL terminated string in the frame's encoding, UTF-16 ends with two NULs
+fn skip_terminated(encoding: u8, bytes: &[u8]) -> Option<&[u8]> {
+    if encoding == 1 || encoding == 2 {
+        let end = bytes.chunks_exact(2).position(|pair| pair == [0, 0])?;
+        bytes.get(end * 2 + 2..)
+    } else {
+        let end = bytes.iter().position(|b| *b == 0)?;
+        bytes.get(end + 1..)
+    }
+}
+
+fn apic(body: &[u8]) -> Option<Vec<u8>> {
+    let (encoding, rest) = body.split_first()?;
+    let rest = skip_terminated(0, rest)?;
+    // Picture type, then the description
+    let rest = skip_terminated(*encoding, rest.get(1..)?)?;
+    Some(rest.to_vec())
+}
+
+// ID3v2.2 has a three letter image format instead of a MIME type
+fn pic(body: &[u8]) -> Option<Vec<u8>> {
+    let encoding = *body.first()?;
+    let rest = skip_terminated(encoding, body.get(5..)?)?;
+    Some(rest.to_vec())
+}
+
+// "3/12" or "3"
+fn track_numbers(track: &str) -> (Option<u32>, Option<u32>) {
+    let mut parts = track.split('/').map(|p| p.trim().parse().ok());
+    (parts.next().flatten(), parts.next().flatten())
+}
+
+// The fixed size tag in the last 128 bytes
+fn id3v1(data: &[u8]) -> Option<Tags> {
+    let tag = data.get(data.len().checked_sub(128)?..)?;
+    if &tag[..3] != b"TAG" {
+        return None;
+    }
+    let field = |range: std::ops::Range<usize>| {
+        let text = decode(0, &tag[range]);
+        let text = text.trim_end_matches('\0').trim().to_owned();
+        (!text.is_empty()).then_some(text)
+    };
+    Some(Tags {
+        title: field(3..33),
+        artist: field(33..63),
+        album: field(63..93),
+        // ID3v1.1 keeps the track in the last byte of the comment
+        track: (tag[125] == 0 && tag[126] != 0).then_some(tag[126] as u32),
+        ..Tags::default()
+    })
+}
+
+// Duration and bitrate from the first MPEG audio frame, exact if it carries a Xing or VBRI header.
+// `audio` starts where the frames do, `audio_bytes` is how long they run in the file
+fn mpeg(audio: &[u8], audio_bytes: u64) -> Option<Tags> {
+    const BITRATES: [[u32; 15]; 5] = [
+        [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448],
+        [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],
+        [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+        [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],
+        [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+    ];
+    const SAMPLE_RATES: [[u32; 3]; 3] = [[44100, 48000, 32000], [22050, 24000, 16000], [11025, 12000, 8000]];
+
+    // Junk before the first frame is skipped, but not forever
+    let start = audio.windows(2).take(64 * 1024).position(|w| w[0] == 0xff && w[1] & 0xe0 == 0xe0)?;
+    let frame = &audio[start..];
+    let header = frame.get(0..4)?;
+
+    let version = (header[1] >> 3) & 3;
+    let layer = (header[1] >> 1) & 3;
+    let bitrate_index = (header[2] >> 4) as usize;
+    let rate_index = ((header[2] >> 2) & 3) as usize;
+    if version == 1 || layer == 0 || bitrate_index == 0 || bitrate_index == 15 || rate_index == 3 {
+        return None;
+    }
+    let mpeg1 = version == 3;
+    let table = match (mpeg1, layer) {
+        (true, 3) => 0,
+        (true, 2) => 1,
+        (true, _) => 2,
+        (false, 3) => 3,
+        (false, _) => 4,
+    };
+    let bitrate = BITRATES[table][bitrate_index];
+    let sample_rate = SAMPLE_RATES[match version { 3 => 0, 2 => 1, _ => 2 }][rate_index];
+    let samples_per_frame = match (layer, mpeg1) {
+        (3, _) => 384,
+        (2, _) | (1, true) => 1152,
+        _ => 576,
+    };
+
+    let mono = header[3] >> 6 == 3;
+    let side_info = match (mpeg1, mono) {
+        (true, false) => 32,
+        (true, true) | (false, false) => 17,
+        (false, true) => 9,
+    };
+    let xing = frame.get(4 + side_info..)
+        .filter(|x| x.starts_with(b"Xing") || x.starts_with(b"Info"))
+        .and_then(|x| (mp4::u32_at(x, 4)? & 1 != 0).then(|| mp4::u32_at(x, 8)).flatten());
+    let vbri = frame.get(36..).filter(|v| v.starts_with(b"VBRI")).and_then(|v| mp4::u32_at(v, 14));
+
+    let bytes = audio_bytes.saturating_sub(start as u64);
+    let (duration, bitrate) = match xing.or(vbri).filter(|frames| *frames > 0) {
+        Some(frames) => {
+            let seconds = frames as f64 * samples_per_frame as f64 / sample_rate as f64;
+            (seconds, (bytes as f64 * 8.0 / seconds / 1000.0).round() as u32)
+        }
+        None => (bytes as f64 * 8.0 / (bitrate as f64 * 1000.0), bitrate),
+    };
+    Some(Tags {
+        duration: Duration::try_from_secs_f64(duration).ok(),
+        bitrate: Some(bitrate),
+        ..Tags::default()
+    })
+}
+
+// The metadata blocks in front of the audio frames
+fn flac_head<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    let mut end = 4;
+    while end < MAX_HEADER_SIZE {
+        let header = read_at(reader, end, 4)?;
+        let [flags, a, b, c] = header[..] else { break };
+        end += 4 + u32::from_be_bytes([0, a, b, c]) as u64;
+        if flags & 0x80 != 0 {
+            break;
+        }
+    }
+    read_at(reader, 0, end.min(MAX_HEADER_SIZE))
+}
+
+// `len` is the size of the whole file, `data` only its metadata blocks
+fn flac(data: &[u8], len: u64) -> Option<Tags> {
+    if data.get(0..4)? != b"fLaC" {
+        return None;
+    }
+    let mut tags = Tags::default();
+    let mut rest = &data[4..];
+    while rest.len() >= 4 {
+        let last = rest[0] & 0x80 != 0;
+        let kind = rest[0] & 0x7f;
+        let block_len = u32::from_be_bytes([0, rest[1], rest[2], rest[3]]) as usize;
+        let Some(body) = rest.get(4..4 + block_len) else { break };
+        match kind {
+            0 if body.len() >= 18 => {
+                let sample_rate = (body[10] as u32) << 12 | (body[11] as u32) << 4 | (body[12] as u32) >> 4;
+                let samples = ((body[13] & 0x0f) as u64) << 32 | mp4::u32_at(body, 14)? as u64;
+                if sample_rate > 0 && samples > 0 {
+                    let seconds = samples as f64 / sample_rate as f64;
+                    tags.duration = Duration::try_from_secs_f64(seconds).ok();
+                    tags.bitrate = Some((len as f64 * 8.0 / seconds / 1000.0).round() as u32);
+                }
+            }
+            4 => tags = vorbis_comments(body).or(tags),
+            6 if tags.cover.is_none() => tags.cover = flac_picture(body),
+            _ => {}
+        }
+        if last {
+            break;
+        }
+        rest = &rest[4 + block_len..];
+    }
+    Some(tags)
+}
+
+// Also used base64 encoded inside Vorbis comments
+fn flac_picture(body: &[u8]) -> Option<Vec<u8>> {
+    let mime = mp4::u32_at(body, 4)? as usize;
+    let description = mp4::u32_at(body, 8 + mime)? as usize;
+    let data = 8 + mime + 4 + description + 16;
+    let len = mp4::u32_at(body, data)? as usize;
+    body.get(data + 4..data + 4 + len).map(<[u8]>::to_vec)
+}
+
+// Little endian "KEY=value" pairs after a vendor string
+fn vorbis_comments(body: &[u8]) -> Tags {
+    let mut tags = Tags::default();
+    let u32_le = |offset: usize| body.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize);
+
+    let Some(vendor) = u32_le(0) else { return tags };
+    let Some(count) = u32_le(4 + vendor) else { return tags };
+    let mut offset = 8 + vendor;
+    for _ in 0..count {
+        let Some(len) = u32_le(offset) else { break };
+        let Some(comment) = body.get(offset + 4..offset + 4 + len) else { break };
+        offset += 4 + len;
+
+        let comment = String::from_utf8_lossy(comment);
+        let Some((key, value)) = comment.split_once('=') else { continue };
+        let value = value.trim().to_owned();
+        match key.to_uppercase().as_str() {
+            "TITLE" => tags.title = tags.title.or(Some(value)),
+            "ARTIST" => tags.artist = tags.artist.or(Some(value)),
+            "ALBUM" => tags.album = tags.album.or(Some(value)),
+            "TRACKNUMBER" => {
+                let (track, total) = track_numbers(&value);
+                tags.track = track;
+                tags.track_total = tags.track_total.or(total);
+            }
+            "TRACKTOTAL" | "TOTALTRACKS" => tags.track_total = value.parse().ok(),
+            "METADATA_BLOCK_PICTURE" if tags.cover.is_none() => {
+                tags.cover = base64(&value).and_then(|picture| flac_picture(&picture));
+            }
+            _ => {}
+        }
+    }
+    tags
+}
+
+fn base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+// The pages holding the identification and comment packets
+fn ogg_head<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    let mut end = 0;
+    let mut packets = 0;
+    while packets < 2 && end < MAX_HEADER_SIZE {
+        let header = read_at(reader, end, 27 + 255)?;
+        let Some(&segments) = header.get(26).filter(|_| header.starts_with(b"OggS")) else { break };
+        let Some(table) = header.get(27..27 + segments as usize) else { break };
+        // A segment shorter than 255 bytes ends a packet
+        packets += table.iter().filter(|len| **len < 255).count();
+        end += 27 + segments as u64 + table.iter().map(|len| *len as u64).sum::<u64>();
+    }
+    read_at(reader, 0, end.min(MAX_HEADER_SIZE))
+}
+
+// Vorbis or Opus in an Ogg container. `head` holds the first pages, `tail` the last ones
+fn ogg(head: &[u8], tail: &[u8], len: u64) -> Option<Tags> {
+    let packets = ogg_packets(head, 2);
+    let (identification, comments) = (packets.first()?, packets.get(1)?);
+
+    let (mut tags, sample_rate) = if identification.starts_with(b"\x01vorbis") {
+        let rate = u32::from_le_bytes(identification.get(12..16)?.try_into().ok()?);
+        (vorbis_comments(comments.get(7..)?), rate)
+    } else if identification.starts_with(b"OpusHead") {
+        // Opus always counts samples at 48 kHz
+        (vorbis_comments(comments.get(8..)?), 48000)
+    } else {
+        return None;
+    };
+
+    // The granule position of the last page is the number of samples
+    let last = tail.windows(4).rposition(|w| w == b"OggS")?;
+    let samples = u64::from_le_bytes(tail.get(last + 6..last + 14)?.try_into().ok()?);
+    if sample_rate > 0 && samples > 0 && samples != u64::MAX {
+        let seconds = samples as f64 / sample_rate as f64;
+        tags.duration = Duration::try_from_secs_f64(seconds).ok();
+        tags.bitrate = Some((len as f64 * 8.0 / seconds / 1000.0).round() as u32);
+    }
+    Some(tags)
+}
+
+// The first `count` packets of the file, a packet may span several pages
+fn ogg_packets(data: &[u8], count: usize) -> Vec<Vec<u8>> {
+    let mut packets = vec![];
+    let mut packet = vec![];
+    let mut rest = data;
+    while packets.len() < count && rest.starts_with(b"OggS") && rest.len() > 27 {
+        let segments = rest[26] as usize;
+        let Some(table) = rest.get(27..27 + segments) else { break };
+        let mut offset = 27 + segments;
+        for &len in table {
+            let Some(segment) = rest.get(offset..offset + len as usize) else { return packets };
+            packet.extend_from_slice(segment);
+            offset += len as usize;
+            // A segment shorter than 255 bytes ends the packet
+            if len < 255 {
+                packets.push(std::mem::take(&mut packet));
+                if packets.len() == count {
+                    return packets;
+                }
+            }
+        }
+        rest = &rest[offset..];
+    }
+    packets
+}
+
+// iTunes style metadata in moov/udta/meta/ilst, `len` is the size of the whole file
+fn m4a(moov: &[u8], len: u64) -> Option<Tags> {
+    let mut tags = Tags::default();
+
+    let timing = mp4::child(moov, b"mvhd").and_then(mp4::timing).filter(|(timescale, duration)| *timescale > 0 && *duration > 0);
+    if let Some((timescale, duration)) = timing {
+        let seconds = duration as f64 / timescale as f64;
+        tags.duration = Duration::try_from_secs_f64(seconds).ok();
+        tags.bitrate = Some((len as f64 * 8.0 / seconds / 1000.0).round() as u32);
+    }
+
+    let Some(meta) = mp4::find(moov, &[b"udta", b"meta"]) else { return Some(tags) };
+    // Usually a full box, but not in files written by QuickTime
+    let meta = if meta.get(4..8) == Some(b"hdlr") { meta } else { mp4::full_box(meta)?.1 };
+    let Some(items) = mp4::child(meta, b"ilst") else { return Some(tags) };
+
+    for (kind, item) in mp4::atoms(items) {
+        // Type indicator and locale come before the value
+        let Some(value) = mp4::child(item, b"data").and_then(|data| data.get(8..)) else { continue };
+        let text = || Some(String::from_utf8_lossy(value).trim().to_owned()).filter(|t| !t.is_empty());
+        match &kind {
+            b"\xa9nam" => tags.title = text(),
+            b"\xa9ART" => tags.artist = text(),
+            b"aART" if tags.artist.is_none() => tags.artist = text(),
+            b"\xa9alb" => tags.album = text(),
+            b"trkn" => {
+                tags.track = mp4::u16_at(value, 2).filter(|t| *t > 0).map(u32::from);
+                tags.track_total = mp4::u16_at(value, 4).filter(|t| *t > 0).map(u32::from);
+            }
+            b"covr" if tags.cover.is_none() => tags.cover = Some(value.to_vec()),
+            _ => {}
+        }
+    }
+    Some(tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id3_frame(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        [&id[..], &(body.len() as u32).to_be_bytes(), &[0, 0], body].concat()
+    }
+
+    fn id3_tag(frames: &[u8]) -> Vec<u8> {
+        let size = frames.len();
+        let synchsafe = [(size >> 21) as u8 & 0x7f, (size >> 14) as u8 & 0x7f, (size >> 7) as u8 & 0x7f, size as u8 & 0x7f];
+        [&b"ID3\x03\0\0"[..], &synchsafe, frames].concat()
+    }
+
+    fn ogg_page(granule: u64, packets: &[&[u8]]) -> Vec<u8> {
+        let table = packets.iter().map(|p| p.len() as u8).collect::<Vec<_>>();
+        [&b"OggS\0\0"[..], &granule.to_le_bytes(), &[0; 12], &[table.len() as u8], &table, &packets.concat()].concat()
+    }
+
+    fn vorbis_identification(rate: u32) -> Vec<u8> {
+        [&b"\x01vorbis"[..], &[0; 5], &rate.to_le_bytes(), &[0; 14]].concat()
+    }
+
+    #[test]
+    fn reads_id3v2_frames() {
+        let tag = id3_tag(&[id3_frame(b"TIT2", b"\x03Song"), id3_frame(b"TRCK", b"\x003/12")].concat());
+        let (tags, len) = id3v2(&tag).unwrap();
+        assert_eq!(tags.title.as_deref(), Some("Song"));
+        assert_eq!((tags.track, tags.track_total), (Some(3), Some(12)));
+        assert_eq!(len, tag.len());
+    }
+
+    #[test]
+    fn truncated_id3v2_tags_do_not_panic() {
+        assert!(id3v2(b"ID3").is_none());
+        assert!(id3v2(b"ID3\x04\0").is_none());
+        // The tag claims more than there is and the frame runs past its end
+        let tag = id3_tag(&id3_frame(b"TIT2", b"\x03Song"));
+        let (tags, _) = id3v2(&tag[..tag.len() - 2]).unwrap();
+        assert_eq!(tags.title, None);
+        // An extended header that isn't there
+        assert!(id3v2(b"ID3\x03\0\x40\0\0\0\x02ab").is_none());
+        assert!(mp3(b"ID3", &[], 3).is_some());
+    }
+
+    #[test]
+    fn id3v1_needs_the_whole_tag() {
+        assert!(id3v1(b"TAG").is_none());
+        let mut tag = [0; 128];
+        tag[..3].copy_from_slice(b"TAG");
+        tag[3..7].copy_from_slice(b"Song");
+        tag[126] = 7;
+        let tags = id3v1(&tag).unwrap();
+        assert_eq!((tags.title.as_deref(), tags.track), (Some("Song"), Some(7)));
+    }
+
+    #[test]
+    fn truncated_flac_blocks_are_skipped() {
+        assert!(flac(b"fLa", 3).is_none());
+        // A stream info block cut off after its header
+        let tags = flac(b"fLaC\x80\0\0\x22\0\0", 10).unwrap();
+        assert_eq!(tags.duration, None);
+    }
+
+    #[test]
+    fn ogg_durations_that_do_not_fit_are_unknown() {
+        let identification = vorbis_identification(1);
+        let head = ogg_page(0, &[&identification, b"\x03vorbis\0\0\0\0\0\0\0\0"]);
+        let tags = ogg(&head, &ogg_page(u64::MAX - 1, &[]), 1000).unwrap();
+        assert_eq!(tags.duration, None);
+
+        let identification = vorbis_identification(48000);
+        let head = ogg_page(0, &[&identification, b"\x03vorbis\0\0\0\0\0\0\0\0"]);
+        let tags = ogg(&head, &ogg_page(96000, &[]), 1000).unwrap();
+        assert_eq!(tags.duration, Some(Duration::from_secs(2)));
+        // The last page is cut off before its granule position
+        assert!(ogg(&head, b"OggS\0", 1000).is_none());
+        assert!(ogg(&head[..30], &[], 1000).is_none());
+    }
+
+    #[test]
+    fn m4a_durations_of_all_ones_are_unknown() {
+        let mvhd = |version: u8, duration: u64| {
+            let body = [&[version, 0, 0, 0][..], &[0; 16], &1u32.to_be_bytes(), &duration.to_be_bytes()].concat();
+            [&(body.len() as u32 + 8).to_be_bytes()[..], b"mvhd", &body].concat()
+        };
+        assert_eq!(m4a(&mvhd(1, u64::MAX), 1000).unwrap().duration, None);
+        assert_eq!(m4a(&mvhd(1, u64::MAX - 1), 1000).unwrap().duration, None);
+        assert_eq!(m4a(&mvhd(1, 90), 1000).unwrap().duration, Some(Duration::from_secs(90)));
+        assert_eq!(m4a(&mvhd(1, 90)[..20], 1000).unwrap().duration, None);
+    }
+}
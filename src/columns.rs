@@ -0,0 +1,225 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::components::fileitem::FileData;
+use crate::platform::Platform;
+use crate::sorting::SortBy;
+use crate::utils::{mime_from_extension, readable_duration, readable_permissions, readable_size, readable_time};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ColumnKind {
+    Name,
+    Size,
+    CreatedAt,
+    LastModified,
+    Accessed,
+    Permissions,
+    Owner,
+    Group,
+    MimeType,
+    Extension,
+    Inode,
+    Links,
+    ItemCount,
+    DateTaken,
+    Title,
+    Artist,
+    Album,
+    Track,
+    Duration,
+    Bitrate,
+    Resolution,
+}
+
+impl ColumnKind {
+    pub const ALL: [ColumnKind; 21] = [
+        ColumnKind::Name,
+        ColumnKind::Size,
+        ColumnKind::CreatedAt,
+        ColumnKind::LastModified,
+        ColumnKind::Accessed,
+        ColumnKind::Permissions,
+        ColumnKind::Owner,
+        ColumnKind::Group,
+        ColumnKind::MimeType,
+        ColumnKind::Extension,
+        ColumnKind::Inode,
+        ColumnKind::Links,
+        ColumnKind::ItemCount,
+        ColumnKind::DateTaken,
+        ColumnKind::Title,
+        ColumnKind::Artist,
+        ColumnKind::Album,
+        ColumnKind::Track,
+        ColumnKind::Duration,
+        ColumnKind::Bitrate,
+        ColumnKind::Resolution,
+    ];
+
+    // Read from the tags of audio files
+    pub const TAGS: [ColumnKind; 6] = [
+        ColumnKind::Title,
+        ColumnKind::Artist,
+        ColumnKind::Album,
+        ColumnKind::Track,
+        ColumnKind::Duration,
+        ColumnKind::Bitrate,
+    ];
+
+    // Read from the headers of video files
+    pub const VIDEO: [ColumnKind; 2] = [ColumnKind::Duration, ColumnKind::Resolution];
+
+    pub fn sort_by(&self) -> SortBy {
+        match self {
+            ColumnKind::Name => SortBy::Name,
+            ColumnKind::Size => SortBy::Size,
+            ColumnKind::CreatedAt => SortBy::CreatedAt,
+            ColumnKind::LastModified => SortBy::LastModified,
+            ColumnKind::Accessed => SortBy::Accessed,
+            ColumnKind::Permissions => SortBy::Permissions,
+            ColumnKind::Owner => SortBy::Owner,
+            ColumnKind::Group => SortBy::Group,
+            ColumnKind::MimeType => SortBy::MimeType,
+            ColumnKind::Extension => SortBy::Extension,
+            ColumnKind::Inode => SortBy::Inode,
+            ColumnKind::Links => SortBy::Links,
+            ColumnKind::ItemCount => SortBy::ItemCount,
+            ColumnKind::DateTaken => SortBy::DateTaken,
+            ColumnKind::Title => SortBy::Title,
+            ColumnKind::Artist => SortBy::Artist,
+            ColumnKind::Album => SortBy::Album,
+            ColumnKind::Track => SortBy::Track,
+            ColumnKind::Duration => SortBy::Duration,
+            ColumnKind::Bitrate => SortBy::Bitrate,
+            ColumnKind::Resolution => SortBy::Resolution,
+        }
+    }
+
+    // Width in pixels a column gets when it is first shown
+    pub fn default_width(&self) -> f32 {
+        match self {
+            ColumnKind::Name => 320.0,
+            ColumnKind::CreatedAt | ColumnKind::LastModified | ColumnKind::Accessed | ColumnKind::MimeType | ColumnKind::DateTaken => 170.0,
+            ColumnKind::Title | ColumnKind::Artist | ColumnKind::Album => 160.0,
+            ColumnKind::Permissions | ColumnKind::Owner | ColumnKind::Group | ColumnKind::Bitrate | ColumnKind::Resolution => 100.0,
+            _ => 80.0,
+        }
+    }
+
+    pub fn value(&self, data: &FileData) -> String {
+        let unix = data.unix.filter(|_| data.metadata_loaded);
+        let tags = data.tags.as_ref();
+        match self {
+            ColumnKind::Name => data.name.clone(),
+            ColumnKind::Size => if !data.is_dir && data.metadata_loaded { readable_size(data.size.unwrap_or(0)) } else { "-".to_owned() },
+            ColumnKind::CreatedAt => readable_time(data.created),
+            ColumnKind::LastModified => readable_time(data.last_modified),
+            ColumnKind::Accessed => readable_time(data.accessed),
+            ColumnKind::Permissions => unix.map(|u| readable_permissions(u.mode, data.is_dir)).unwrap_or("-".to_owned()),
+            ColumnKind::Owner => unix.map(|u| Platform::user_name(u.uid)).unwrap_or("-".to_owned()),
+            ColumnKind::Group => unix.map(|u| Platform::group_name(u.gid)).unwrap_or("-".to_owned()),
+            ColumnKind::MimeType => if data.is_dir { "inode/directory".to_owned() } else { mime_from_extension(&data.extension()).to_owned() },
+            ColumnKind::Extension => data.extension(),
+            ColumnKind::Inode => unix.map(|u| u.inode.to_string()).unwrap_or("-".to_owned()),
+            ColumnKind::Links => unix.map(|u| u.links.to_string()).unwrap_or("-".to_owned()),
+            ColumnKind::ItemCount => data.item_count.map(|c| format!("{} items", c)).unwrap_or("-".to_owned()),
+            ColumnKind::DateTaken => readable_time(data.taken),
+            ColumnKind::Title => tags.and_then(|t| t.title.clone()).unwrap_or("-".to_owned()),
+            ColumnKind::Artist => tags.and_then(|t| t.artist.clone()).unwrap_or("-".to_owned()),
+            ColumnKind::Album => tags.and_then(|t| t.album.clone()).unwrap_or("-".to_owned()),
+            ColumnKind::Track => tags.and_then(|t| t.track_label()).unwrap_or("-".to_owned()),
+            ColumnKind::Duration => readable_duration(data.duration()),
+            ColumnKind::Bitrate => tags.and_then(|t| t.bitrate).map(|b| format!("{} kbit/s", b)).unwrap_or("-".to_owned()),
+            ColumnKind::Resolution => data.video.as_ref().and_then(|v| v.resolution).map(|(w, h)| format!("{}×{}", w, h)).unwrap_or("-".to_owned()),
+        }
+    }
+}
+
+impl fmt::Display for ColumnKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            ColumnKind::Name => "Name",
+            ColumnKind::Size => "Size",
+            ColumnKind::CreatedAt => "Created At",
+            ColumnKind::LastModified => "Last Modified",
+            ColumnKind::Accessed => "Accessed",
+            ColumnKind::Permissions => "Permissions",
+            ColumnKind::Owner => "Owner",
+            ColumnKind::Group => "Group",
+            ColumnKind::MimeType => "MIME Type",
+            ColumnKind::Extension => "Extension",
+            ColumnKind::Inode => "Inode",
+            ColumnKind::Links => "Links",
+            ColumnKind::ItemCount => "Items",
+            ColumnKind::DateTaken => "Date Taken",
+            ColumnKind::Title => "Title",
+            ColumnKind::Artist => "Artist",
+            ColumnKind::Album => "Album",
+            ColumnKind::Track => "Track",
+            ColumnKind::Duration => "Duration",
+            ColumnKind::Bitrate => "Bitrate",
+            ColumnKind::Resolution => "Resolution",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ColumnSpec {
+    pub kind: ColumnKind,
+    pub width: f32,
+}
+
+// The columns shown in the list, in order, shared by the table header and every row
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnLayout {
+    pub columns: Vec<ColumnSpec>,
+}
+
+impl Default for ColumnLayout {
+    fn default() -> Self {
+        Self {
+            columns: [ColumnKind::Name, ColumnKind::Size, ColumnKind::CreatedAt, ColumnKind::LastModified]
+                .into_iter()
+                .map(|kind| ColumnSpec { kind, width: kind.default_width() })
+                .collect(),
+        }
+    }
+}
+
+impl ColumnLayout {
+    pub const MIN_WIDTH: f32 = 40.0;
+
+    pub fn only(kind: ColumnKind, width: f32) -> Self {
+        Self {
+            columns: vec![ColumnSpec { kind, width }],
+        }
+    }
+
+    pub fn contains(&self, kind: ColumnKind) -> bool {
+        self.columns.iter().any(|c| c.kind == kind)
+    }
+
+    pub fn show(&mut self, kind: ColumnKind) {
+        if !self.contains(kind) {
+            self.columns.push(ColumnSpec { kind, width: kind.default_width() });
+        }
+    }
+
+    pub fn hide(&mut self, kind: ColumnKind) {
+        self.columns.retain(|c| c.kind != kind);
+    }
+
+    pub fn resize(&mut self, index: usize, delta: f32) {
+        if let Some(column) = self.columns.get_mut(index) {
+            column.width = (column.width + delta).max(Self::MIN_WIDTH);
+        }
+    }
+
+    pub fn reorder(&mut self, from: usize, to: usize) {
+        if from < self.columns.len() && to < self.columns.len() {
+            let column = self.columns.remove(from);
+            self.columns.insert(to, column);
+        }
+    }
+}
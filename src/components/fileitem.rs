@@ -1,10 +1,18 @@
-use std::{path::PathBuf, time::SystemTime};
+use std::{path::PathBuf, time::{Duration, SystemTime}};
 
-use iced::{widget::{container, mouse_area, row, text, Image, Row}, Color, Element, Length, Padding, Task};
+use iced::{widget::{button, container, mouse_area, row, text, Image, Row, Space}, Color, Element, Padding, Task};
 
-use crate::utils::{file_type_from_extension, image_from_type, readable_size, readable_time};
+use crate::audio::{self, Tags};
+use crate::columns::{ColumnKind, ColumnLayout};
+use crate::document::{self, DocumentInfo};
+use crate::photo;
+use crate::utils::{file_type_from_extension, image_from_type};
+use crate::video::{self, VideoInfo};
 use crate::views::explorer::Message;
 
+const INDENT: f32 = 16.0;
+const DISCLOSURE_WIDTH: f32 = 14.0;
+
 #[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub enum FileType {
     Directory,
@@ -18,6 +26,16 @@ pub enum FileType {
     Unknown,
 }
 
+// Metadata that only exists on unix-like systems
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnixMetadata {
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub inode: u64,
+    pub links: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct FileData {
     pub name: String,
@@ -26,32 +44,129 @@ pub struct FileData {
     pub size: Option<u64>,
     pub last_modified: Option<SystemTime>,
     pub created: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
     pub file_type: Option<FileType>,
+    pub unix: Option<UnixMetadata>,
+    // Number of entries inside a directory, only counted when the column is shown
+    pub item_count: Option<usize>,
+    // When a photo was taken, from its EXIF data, only read when the column is shown
+    pub taken: Option<SystemTime>,
+    // Song tags without the cover, only read when one of their columns is shown
+    pub tags: Option<Tags>,
+    // Container metadata of videos, only read when one of its columns is shown
+    pub video: Option<VideoInfo>,
+    // Title, author and such of documents, only read while searching
+    pub document: Option<DocumentInfo>,
+    pub metadata_loaded: bool,
 }
 
 
 
 impl FileData {
     pub fn new(path: PathBuf) -> Self {
-        let name = path.file_name().unwrap().to_string_lossy().to_string();
         let is_dir = path.is_dir();
-        let metadata = path.metadata().unwrap();
-        let created = metadata.created().ok();
-        let last_modified = metadata.modified().ok();
-        let size = if is_dir { None } else { Some(metadata.len()) };
+        Self::lazy(path, is_dir).with_metadata()
+    }
+
+    // Only the name and kind are known, metadata is filled in later by `with_metadata`
+    pub fn lazy(path: PathBuf, is_dir: bool) -> Self {
+        let name = path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string_lossy().to_string());
         Self {
             name,
             path: path.clone(),
             is_dir,
-            size,
-            last_modified,
-            created,
+            size: None,
+            last_modified: None,
+            created: None,
+            accessed: None,
             file_type: if is_dir {
                 Some(FileType::Directory)
             } else {
                 Some(file_type_from_extension(path.extension().and_then(|s| s.to_str()).unwrap_or("")))
             },
+            unix: None,
+            item_count: None,
+            taken: None,
+            tags: None,
+            video: None,
+            document: None,
+            metadata_loaded: false,
+        }
+    }
+
+    pub fn with_metadata(mut self) -> Self {
+        if let Ok(metadata) = self.path.metadata() {
+            self.created = metadata.created().ok();
+            self.last_modified = metadata.modified().ok();
+            self.accessed = metadata.accessed().ok();
+            self.size = if self.is_dir { None } else { Some(metadata.len()) };
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                self.unix = Some(UnixMetadata {
+                    mode: metadata.mode(),
+                    uid: metadata.uid(),
+                    gid: metadata.gid(),
+                    inode: metadata.ino(),
+                    links: metadata.nlink(),
+                });
+            }
+        }
+        self.metadata_loaded = true;
+        self
+    }
+
+    pub fn with_item_count(mut self) -> Self {
+        if self.is_dir {
+            self.item_count = std::fs::read_dir(&self.path).ok().map(|entries| entries.count());
+        }
+        self
+    }
+
+    pub fn with_taken(mut self) -> Self {
+        if self.file_type == Some(FileType::Image) {
+            self.taken = photo::taken(&self.path);
+        }
+        self
+    }
+
+    pub fn with_tags(mut self) -> Self {
+        if self.file_type == Some(FileType::Audio) {
+            self.tags = audio::read(&self.path, false);
+        }
+        self
+    }
+
+    pub fn with_video(mut self) -> Self {
+        if self.file_type == Some(FileType::Video) {
+            self.video = video::read(&self.path);
+        }
+        self
+    }
+
+    pub fn with_document(mut self) -> Self {
+        if self.file_type == Some(FileType::Document) {
+            self.document = document::read(&self.path);
         }
+        self
+    }
+
+    // Length of a song or a video
+    pub fn duration(&self) -> Option<Duration> {
+        self.tags.as_ref().and_then(|t| t.duration)
+            .or_else(|| self.video.as_ref().and_then(|v| v.duration))
+    }
+
+    pub fn extension(&self) -> String {
+        if self.is_dir {
+            return "".to_owned();
+        }
+        self.path.extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
     }
 
     pub fn parent(current_path: PathBuf) -> Self {
@@ -62,7 +177,15 @@ impl FileData {
             size: None,
             last_modified: None,
             created: None,
+            accessed: None,
             file_type: Some(FileType::Directory),
+            unix: None,
+            item_count: None,
+            taken: None,
+            tags: None,
+            video: None,
+            document: None,
+            metadata_loaded: true,
         }
     }
 }
@@ -73,11 +196,13 @@ pub struct FileItem<Message> {
     highlighted: bool,
     on_select: Option<Box<dyn Fn(FileData) -> Message>>,
     on_open: Option<Box<dyn Fn(FileData) -> Message>>,
+    on_middle_click: Option<Box<dyn Fn(FileData) -> Message>>,
+    on_toggle: Option<Box<dyn Fn(FileData) -> Message>>,
 
-    hide_name: bool,
-    hide_size: bool,
-    hide_created: bool,
-    hide_last_modified: bool,
+    columns: ColumnLayout,
+    // Nesting level in the tree view
+    depth: u16,
+    expanded: bool,
 }
 
 impl FileItem<Message> {
@@ -87,10 +212,11 @@ impl FileItem<Message> {
             highlighted: false,
             on_select: None,
             on_open: None,
-            hide_name: false,
-            hide_size: false,
-            hide_created: false,
-            hide_last_modified: false,
+            on_middle_click: None,
+            on_toggle: None,
+            columns: ColumnLayout::default(),
+            depth: 0,
+            expanded: false,
         }
     }
 
@@ -100,10 +226,11 @@ impl FileItem<Message> {
             highlighted: false,
             on_select: None,
             on_open: None,
-            hide_name: false,
-            hide_size: false,
-            hide_created: false,
-            hide_last_modified: false,
+            on_middle_click: None,
+            on_toggle: None,
+            columns: ColumnLayout::default(),
+            depth: 0,
+            expanded: false,
         }
     }
 
@@ -116,6 +243,25 @@ impl FileItem<Message> {
     pub fn view<'a>(self) -> Element<'a, Message> {
         let mut data: Row<'a, Message> = Row::new().spacing(5);
 
+        // The indent and disclosure triangle are taken out of the name column so the others stay aligned
+        let mut indent = 0.0;
+        if let Some(toggle) = &self.on_toggle {
+            indent = self.depth as f32 * INDENT + DISCLOSURE_WIDTH + 5.0;
+            let disclosure: Element<'a, Message> = if self.data.is_dir && self.data.name != ".." {
+                button(text(if self.expanded { "▾" } else { "▸" }).size(14).shaping(text::Shaping::Advanced))
+                    .on_press(toggle(self.data.clone()))
+                    .padding(0)
+                    .style(button::text)
+                    .width(DISCLOSURE_WIDTH)
+                    .into()
+            } else {
+                Space::with_width(DISCLOSURE_WIDTH).into()
+            };
+            data = data
+                .push(Space::with_width(self.depth as f32 * INDENT))
+                .push(disclosure);
+        }
+
         data = data.push(
             container(
                 Image::new(image_from_type(self.data.clone().file_type.unwrap_or(FileType::Unknown)))
@@ -125,31 +271,15 @@ impl FileItem<Message> {
                 .padding(0),
         );
 
-        if !self.hide_name {
-            data = data.push(
-                text(format!("{}", self.data.name))
-                    .width(Length::FillPortion(4))
-                    .size(14),
-            );
-        }
-        if !self.hide_size {
-            data = data.push(
-                text(if !self.data.is_dir { readable_size(self.data.size.unwrap_or(0)) } else { "-".to_owned() })
-                    .width(Length::FillPortion(1))
-                    .size(14),
-            );
-        }
-        if !self.hide_created {
-            data = data.push(
-                text(readable_time(self.data.created))
-                    .width(Length::FillPortion(2))
-                    .size(14),
-            );
-        }
-        if !self.hide_last_modified {
+        for column in &self.columns.columns {
+            let width = if column.kind == ColumnKind::Name {
+                (column.width - indent).max(0.0)
+            } else {
+                column.width
+            };
             data = data.push(
-                text(readable_time(self.data.last_modified))
-                    .width(Length::FillPortion(2))
+                text(column.kind.value(&self.data))
+                    .width(width)
                     .size(14),
             );
         }
@@ -177,6 +307,9 @@ impl FileItem<Message> {
         if let Some(msg) = self.on_open {
             row = row.on_double_click(msg(self.data.clone()));
         }
+        if let Some(msg) = self.on_middle_click {
+            row = row.on_middle_press(msg(self.data.clone()));
+        }
 
         row.into()
     }
@@ -196,10 +329,26 @@ impl FileItem<Message> {
         self
     }
 
+    pub fn on_middle_click(mut self, msg: Box<dyn Fn(FileData) -> Message>) -> Self {
+        self.on_middle_click = Some(msg);
+        self
+    }
+
+    // Shows a disclosure triangle on directories, indented by `depth` levels
+    pub fn on_toggle(mut self, msg: Box<dyn Fn(FileData) -> Message>, depth: u16, expanded: bool) -> Self {
+        self.on_toggle = Some(msg);
+        self.depth = depth;
+        self.expanded = expanded;
+        self
+    }
+
+    pub fn columns(mut self, columns: ColumnLayout) -> Self {
+        self.columns = columns;
+        self
+    }
+
     pub fn sidebar(mut self) -> Self {
-        self.hide_size = true;
-        self.hide_created = true;
-        self.hide_last_modified = true;
+        self.columns = ColumnLayout::only(ColumnKind::Name, 160.0);
         self
     }
 }
@@ -225,10 +374,11 @@ where
             highlighted: false,
             on_select: None,
             on_open: None,
-            hide_name: false,
-            hide_size: false,
-            hide_created: false,
-            hide_last_modified: false,
+            on_middle_click: None,
+            on_toggle: None,
+            columns: ColumnLayout::default(),
+            depth: 0,
+            expanded: false,
         }
     }
 }
\ No newline at end of file
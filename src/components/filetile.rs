@@ -0,0 +1,112 @@
+use iced::widget::image::Handle;
+use iced::{alignment, widget::{column, container, mouse_area, text, Image}, Color, Element, Length};
+
+use crate::components::fileitem::{FileData, FileType};
+use crate::utils::image_from_type;
+use crate::views::explorer::Message;
+
+pub struct FileTile<Message> {
+    pub data: FileData,
+
+    size: f32,
+    thumbnail: Option<Handle>,
+    highlighted: bool,
+    on_select: Option<Box<dyn Fn(FileData) -> Message>>,
+    on_open: Option<Box<dyn Fn(FileData) -> Message>>,
+    on_middle_click: Option<Box<dyn Fn(FileData) -> Message>>,
+}
+
+impl FileTile<Message> {
+    pub fn new(data: FileData, size: f32) -> Self {
+        Self {
+            data,
+            size,
+            thumbnail: None,
+            highlighted: false,
+            on_select: None,
+            on_open: None,
+            on_middle_click: None,
+        }
+    }
+
+    pub fn view<'a>(self) -> Element<'a, Message> {
+        let image = match self.thumbnail {
+            Some(handle) => Image::new(handle),
+            None => Image::new(image_from_type(self.data.clone().file_type.unwrap_or(FileType::Unknown))),
+        };
+
+        let tile = column![
+            container(image.width(Length::Fill).height(Length::Fill))
+                .width(self.size)
+                .height(self.size)
+                .center_x(self.size)
+                .center_y(self.size),
+            text(self.data.name.clone())
+                .size(12)
+                .width(self.size)
+                .height(32)
+                .align_x(alignment::Horizontal::Center),
+        ]
+            .spacing(5);
+
+        let mut tile = mouse_area(
+            container(tile)
+                .padding(5)
+                .style(if self.highlighted {
+                    |_: &_| iced::widget::container::Style {
+                        background: Some(Color::from_rgba(1.0, 1.0, 1.0, 0.06).into()),
+                        ..Default::default()
+                    }
+                } else {
+                    |_: &_| iced::widget::container::Style::default()
+                })
+        );
+
+        if let Some(msg) = self.on_select {
+            tile = tile.on_press(msg(self.data.clone()));
+        }
+        if let Some(msg) = self.on_open {
+            tile = tile.on_double_click(msg(self.data.clone()));
+        }
+        if let Some(msg) = self.on_middle_click {
+            tile = tile.on_middle_press(msg(self.data.clone()));
+        }
+
+        tile.into()
+    }
+
+    pub fn thumbnail(mut self, thumbnail: Option<Handle>) -> Self {
+        self.thumbnail = thumbnail;
+        self
+    }
+
+    pub fn is_highlighted(mut self, highlight: bool) -> Self {
+        self.highlighted = highlight;
+        self
+    }
+
+    pub fn on_select(mut self, msg: Box<dyn Fn(FileData) -> Message>) -> Self {
+        self.on_select = Some(msg);
+        self
+    }
+
+    pub fn on_open(mut self, msg: Box<dyn Fn(FileData) -> Message>) -> Self {
+        self.on_open = Some(msg);
+        self
+    }
+
+    pub fn on_middle_click(mut self, msg: Box<dyn Fn(FileData) -> Message>) -> Self {
+        self.on_middle_click = Some(msg);
+        self
+    }
+}
+
+impl<'a> From<FileTile<Message>>
+    for Element<'a, Message>
+where
+    Message: 'a + Clone,
+{
+    fn from(tile: FileTile<Message>) -> Self {
+        tile.view()
+    }
+}
@@ -1 +1,2 @@
-pub mod fileitem;
\ No newline at end of file
+pub mod fileitem;
+pub mod filetile;
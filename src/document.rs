@@ -0,0 +1,806 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use flate2::read::ZlibDecoder;
+
+use crate::utils::read_at;
+use crate::zip::Archive;
+
+// Streams bigger than this aren't read or inflated
+const MAX_STREAM_SIZE: u64 = 16 * 1024 * 1024;
+
+// Cross-reference sections followed through /Prev, a loop of them ends here
+const MAX_XREF_SECTIONS: usize = 64;
+
+// Cross-reference tables bigger than this aren't read
+const MAX_XREF_SIZE: u64 = 16 * 1024 * 1024;
+
+// Objects are read until "endobj" in growing chunks, but not further than this
+const MAX_OBJECT_SIZE: u64 = 1024 * 1024;
+
+// What the preview and search know about a document
+#[derive(Debug, Clone, Default)]
+pub struct DocumentInfo {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub pages: Option<u32>,
+    // The application that wrote the file
+    pub creator: Option<String>,
+    // "2024-03-01 14:05:00", as written in the file without converting the time zone
+    pub created: Option<String>,
+    pub modified: Option<String>,
+}
+
+impl DocumentInfo {
+    // Label and value of every known field, in the order they're shown
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        [
+            ("Title", self.title.clone()),
+            ("Author", self.author.clone()),
+            ("Pages", self.pages.map(|p| p.to_string())),
+            ("Created With", self.creator.clone()),
+            ("Created", self.created.clone()),
+            ("Modified", self.modified.clone()),
+        ]
+            .into_iter()
+            .filter_map(|(label, value)| value.map(|v| (label, v)))
+            .collect()
+    }
+}
+
+// Reads the metadata by the file's extension
+pub fn read(path: &Path) -> Option<DocumentInfo> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    let file = File::open(path).ok()?;
+    match extension.as_str() {
+        "pdf" => pdf(file),
+        "docx" | "xlsx" | "pptx" => office_open_xml(&mut Archive::open(file)?),
+        "odt" | "ods" | "odp" => open_document(&mut Archive::open(file)?),
+        "epub" => epub(&mut Archive::open(file)?),
+        _ => None,
+    }
+}
+
+// docProps/core.xml for the Dublin Core fields, docProps/app.xml for the application and page count
+fn office_open_xml<R: Read + Seek>(zip: &mut Archive<R>) -> Option<DocumentInfo> {
+    let core = zip.entry("docProps/core.xml").and_then(|xml| String::from_utf8(xml).ok());
+    let app = zip.entry("docProps/app.xml").and_then(|xml| String::from_utf8(xml).ok());
+    let core = core.as_deref().and_then(|xml| roxmltree::Document::parse(xml).ok());
+    let app = app.as_deref().and_then(|xml| roxmltree::Document::parse(xml).ok());
+    if core.is_none() && app.is_none() {
+        return None;
+    }
+
+    let core_text = |name: &str| core.as_ref().and_then(|doc| element_text(doc, name));
+    let app_text = |name: &str| app.as_ref().and_then(|doc| element_text(doc, name));
+    Some(DocumentInfo {
+        title: core_text("title"),
+        author: core_text("creator").or_else(|| core_text("lastModifiedBy")),
+        // Word counts pages, PowerPoint slides, Excel neither
+        pages: app_text("Pages").or_else(|| app_text("Slides")).and_then(|p| p.parse().ok()),
+        creator: app_text("Application"),
+        created: core_text("created").map(|d| xml_date(&d)),
+        modified: core_text("modified").map(|d| xml_date(&d)),
+    })
+}
+
+// OpenDocument keeps everything in meta.xml
+fn open_document<R: Read + Seek>(zip: &mut Archive<R>) -> Option<DocumentInfo> {
+    let xml = String::from_utf8(zip.entry("meta.xml")?).ok()?;
+    let doc = roxmltree::Document::parse(&xml).ok()?;
+    let text = |name: &str| element_text(&doc, name);
+    let statistic = doc.descendants().find(|n| n.tag_name().name() == "document-statistic");
+    let count = |name: &str| statistic.and_then(|s| s.attributes().find(|a| a.name() == name)).and_then(|a| a.value().parse().ok());
+
+    Some(DocumentInfo {
+        title: text("title"),
+        author: text("initial-creator").or_else(|| text("creator")),
+        pages: count("page-count"),
+        creator: text("generator"),
+        created: text("creation-date").map(|d| xml_date(&d)),
+        modified: text("date").map(|d| xml_date(&d)),
+    })
+}
+
+// The OPF package named by META-INF/container.xml
+fn epub<R: Read + Seek>(zip: &mut Archive<R>) -> Option<DocumentInfo> {
+    let container = String::from_utf8(zip.entry("META-INF/container.xml")?).ok()?;
+    let container = roxmltree::Document::parse(&container).ok()?;
+    let package = container.descendants()
+        .find(|n| n.tag_name().name() == "rootfile")
+        .and_then(|n| n.attribute("full-path"))?;
+
+    let xml = String::from_utf8(zip.entry(package)?).ok()?;
+    let doc = roxmltree::Document::parse(&xml).ok()?;
+    let text = |name: &str| element_text(&doc, name);
+    // EPUB 3 keeps the modification date in <meta property="dcterms:modified">
+    let modified = doc.descendants()
+        .find(|n| n.tag_name().name() == "meta" && n.attribute("property") == Some("dcterms:modified"))
+        .and_then(|n| n.text())
+        .map(|d| xml_date(d.trim()));
+    let generator = doc.descendants()
+        .find(|n| n.tag_name().name() == "meta" && n.attribute("name") == Some("generator"))
+        .and_then(|n| n.attribute("content"))
+        .map(str::to_owned);
+
+    Some(DocumentInfo {
+        title: text("title"),
+        author: text("creator"),
+        pages: None,
+        creator: generator,
+        created: text("date").map(|d| xml_date(&d)),
+        modified,
+    })
+}
+
+// Text of the first element with this local name, whatever its namespace
+fn element_text(doc: &roxmltree::Document, name: &str) -> Option<String> {
+    doc.descendants()
+        .find(|n| n.tag_name().name() == name)
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_owned())
+        .filter(|t| !t.is_empty())
+}
+
+// "2024-03-01T14:05:00Z" to "2024-03-01 14:05:00"
+fn xml_date(date: &str) -> String {
+    let date = date.trim_end_matches('Z').replacen('T', " ", 1);
+    date.split('.').next().unwrap_or(&date).to_owned()
+}
+
+// The info dictionary and the page count from the document catalog
+fn pdf<R: Read + Seek>(reader: R) -> Option<DocumentInfo> {
+    let mut pdf = Pdf::open(reader)?;
+    let info = pdf.reference(b"/Info").and_then(|num| pdf.object(num));
+    let pages = pdf.reference(b"/Root")
+        .and_then(|num| pdf.object(num))
+        .and_then(|catalog| match value(&catalog, b"/Pages")? {
+            Value::Ref(num) => pdf.object(num),
+            _ => None,
+        })
+        .and_then(|pages| match value(&pages, b"/Count")? {
+            Value::Number(count) => u32::try_from(count).ok(),
+            _ => None,
+        });
+
+    // Strings of encrypted files can't be read without decrypting them
+    let encrypted = pdf.trailers.iter().any(|trailer| value(trailer, b"/Encrypt").is_some());
+    let info = info.filter(|_| !encrypted).unwrap_or_default();
+
+    Some(DocumentInfo {
+        title: pdf.string(&info, b"/Title"),
+        author: pdf.string(&info, b"/Author"),
+        pages,
+        // The application the document was made in, otherwise the one that turned it into a PDF
+        creator: pdf.string(&info, b"/Creator").or_else(|| pdf.string(&info, b"/Producer")),
+        created: pdf.string(&info, b"/CreationDate").map(|d| pdf_date(&d)),
+        modified: pdf.string(&info, b"/ModDate").map(|d| pdf_date(&d)),
+    })
+}
+
+// A PDF read through its cross-reference sections, only the objects asked for are read
+struct Pdf<R> {
+    reader: R,
+    // Where every object is, the newest revision's entry wins
+    objects: HashMap<u32, Location>,
+    // Trailer dictionaries, the newest first
+    trailers: Vec<Vec<u8>>,
+    // Inflated object streams by their object number, None if they couldn't be read
+    streams: HashMap<u32, Option<ObjectStream>>,
+}
+
+enum Location {
+    Free,
+    Offset(u64),
+    // Kept in the object stream with this number
+    Compressed(u32),
+}
+
+struct ObjectStream {
+    data: Vec<u8>,
+    // Object numbers and where they start in `data`
+    objects: Vec<(u32, usize)>,
+}
+
+impl<R: Read + Seek> Pdf<R> {
+    // Follows the cross-reference sections from the end of the file back to the first revision
+    fn open(mut reader: R) -> Option<Self> {
+        if !read_at(&mut reader, 0, 5)?.starts_with(b"%PDF") {
+            return None;
+        }
+        // The file ends with "startxref", the offset of the newest section and "%%EOF"
+        let len = reader.seek(SeekFrom::End(0)).ok()?;
+        let tail = read_at(&mut reader, len.saturating_sub(1024), 1024)?;
+        let position = tail.windows(9).rposition(|w| w == b"startxref")?;
+        let Value::Number(offset) = value_at(&tail, position + 9)? else { return None };
+
+        let mut pdf = Pdf { reader, objects: HashMap::new(), trailers: vec![], streams: HashMap::new() };
+        let mut visited = HashSet::new();
+        let mut next = u64::try_from(offset).ok();
+        while let Some(offset) = next {
+            if !visited.insert(offset) || visited.len() > MAX_XREF_SECTIONS {
+                break;
+            }
+            let Some(trailer) = pdf.section(offset) else { break };
+            // Hybrid files list the objects only newer readers know about in a stream next to the table
+            if let Some(Value::Number(stream)) = value(&trailer, b"/XRefStm") {
+                u64::try_from(stream).ok().and_then(|stream| pdf.xref_stream(stream));
+            }
+            next = match value(&trailer, b"/Prev") {
+                Some(Value::Number(prev)) => u64::try_from(prev).ok(),
+                _ => None,
+            };
+            pdf.trailers.push(trailer);
+        }
+        (!pdf.trailers.is_empty()).then_some(pdf)
+    }
+
+    // Adds the entries of the section at `offset` that no newer one had, returns its trailer
+    fn section(&mut self, offset: u64) -> Option<Vec<u8>> {
+        if read_at(&mut self.reader, offset, 16)?.trim_ascii_start().starts_with(b"xref") {
+            self.table(offset)
+        } else {
+            self.xref_stream(offset)
+        }
+    }
+
+    // A classic table, subsections of 20 byte entries followed by the trailer
+    fn table(&mut self, offset: u64) -> Option<Vec<u8>> {
+        let head = read_at(&mut self.reader, offset, 16)?;
+        let mut position = offset + find_all(&head, b"xref").next()? as u64 + 4;
+        loop {
+            let line = read_at(&mut self.reader, position, 64)?;
+            let start = line.len() - line.trim_ascii_start().len();
+            if line[start..].starts_with(b"trailer") {
+                return self.read_until(position + start as u64, |data| dictionary(data).map(<[u8]>::to_vec));
+            }
+
+            // "first count" on a line of its own
+            let end = line[start..].iter().position(|b| *b == b'\r' || *b == b'\n')? + start;
+            let header = String::from_utf8_lossy(&line[start..end]).to_string();
+            let mut numbers = header.split_whitespace().map(|n| n.parse::<u64>().ok());
+            let (Some(Some(first)), Some(Some(count))) = (numbers.next(), numbers.next()) else { return None };
+            let eol = line[end..].len() - line[end..].trim_ascii_start().len();
+            position += (end + eol) as u64;
+
+            let size = count.checked_mul(20).filter(|size| *size <= MAX_XREF_SIZE)?;
+            let entries = read_at(&mut self.reader, position, size)?;
+            for (i, entry) in entries.chunks_exact(20).enumerate() {
+                let Some(num) = first.checked_add(i as u64).and_then(|num| u32::try_from(num).ok()) else { break };
+                let location = match entry[17] {
+                    b'n' => Location::Offset(String::from_utf8_lossy(&entry[..10]).parse().ok()?),
+                    _ => Location::Free,
+                };
+                self.objects.entry(num).or_insert(location);
+            }
+            if (entries.len() as u64) < size {
+                return None;
+            }
+            position += size;
+        }
+    }
+
+    // A cross-reference stream of PDF 1.5 and later, its dictionary doubles as the trailer
+    fn xref_stream(&mut self, offset: u64) -> Option<Vec<u8>> {
+        let (_, dict, data) = self.stream_at(offset)?;
+        // Only streams of /Type /XRef
+        find_all(&dict, b"/XRef").next()?;
+        // Widths of the type, offset and generation (or index) fields of every entry
+        let widths = numbers(&dict, b"/W")?;
+        if widths.iter().any(|w| *w > 8) {
+            return None;
+        }
+        let [type_width, offset_width, index_width] = widths[..] else { return None };
+        let (type_width, offset_width, index_width) = (type_width as usize, offset_width as usize, index_width as usize);
+        if type_width + offset_width + index_width == 0 {
+            return None;
+        }
+        let Some(Value::Number(size)) = value(&dict, b"/Size") else { return None };
+        let index = numbers(&dict, b"/Index").unwrap_or(vec![0, u64::try_from(size).ok()?]);
+
+        let field = |entry: &[u8], start: usize, width: usize| entry[start..start + width].iter().fold(0, |v, b| (v << 8) | *b as u64);
+        let mut entries = data.chunks_exact(type_width + offset_width + index_width);
+        for range in index.chunks_exact(2) {
+            for num in range[0]..range[0].saturating_add(range[1]) {
+                let (Some(entry), Ok(num)) = (entries.next(), u32::try_from(num)) else { break };
+                // The type is 1 if its field is left out
+                let kind = if type_width == 0 { 1 } else { field(entry, 0, type_width) };
+                let location = match kind {
+                    0 => Location::Free,
+                    1 => Location::Offset(field(entry, type_width, offset_width)),
+                    2 => match u32::try_from(field(entry, type_width, offset_width)) {
+                        Ok(stream) => Location::Compressed(stream),
+                        Err(_) => continue,
+                    },
+                    // Other types are to be read as null
+                    _ => continue,
+                };
+                self.objects.entry(num).or_insert(location);
+            }
+        }
+        Some(dict)
+    }
+
+    // The object number of "/Key n 0 R" in the newest trailer that has it
+    fn reference(&self, key: &[u8]) -> Option<u32> {
+        self.trailers.iter().find_map(|trailer| match value(trailer, key)? {
+            Value::Ref(num) => Some(num),
+            _ => None,
+        })
+    }
+
+    // The body of object `num` between "obj" and "endobj", or the dictionary of a stream
+    fn object(&mut self, num: u32) -> Option<Vec<u8>> {
+        match *self.objects.get(&num)? {
+            Location::Free => None,
+            Location::Offset(offset) => {
+                let (found, body, _) = self.object_at(offset)?;
+                (found == num).then_some(body)
+            }
+            Location::Compressed(stream) => {
+                let stream = self.object_stream(stream)?;
+                let position = stream.objects.iter().position(|(n, _)| *n == num)?;
+                // An object runs up to where the next one starts, the last one to the end
+                let start = stream.objects[position].1;
+                let end = stream.objects.get(position + 1).map_or(stream.data.len(), |(_, offset)| *offset);
+                stream.data.get(start..end).map(<[u8]>::to_vec)
+            }
+        }
+    }
+
+    fn object_stream(&mut self, num: u32) -> Option<&ObjectStream> {
+        if !self.streams.contains_key(&num) {
+            // Marked as unreadable first so a stream whose length is kept in itself can't recurse
+            self.streams.insert(num, None);
+            let stream = self.read_object_stream(num);
+            self.streams.insert(num, stream);
+        }
+        self.streams.get(&num)?.as_ref()
+    }
+
+    fn read_object_stream(&mut self, num: u32) -> Option<ObjectStream> {
+        let Location::Offset(offset) = *self.objects.get(&num)? else { return None };
+        let (found, dict, data) = self.stream_at(offset)?;
+        let Some(Value::Number(first)) = value(&dict, b"/First") else { return None };
+        let first = usize::try_from(first).ok()?;
+
+        // The stream starts with pairs of object numbers and offsets from `first`
+        let header = String::from_utf8_lossy(data.get(..first)?).to_string();
+        let numbers = header.split_whitespace().map(|n| n.parse::<usize>().ok()).collect::<Option<Vec<_>>>()?;
+        let objects = numbers.chunks_exact(2)
+            .map(|pair| Some((u32::try_from(pair[0]).ok()?, first.checked_add(pair[1])?)))
+            .collect::<Option<Vec<_>>>()?;
+        (found == num).then_some(ObjectStream { data, objects })
+    }
+
+    // Number, dictionary and decoded data of the stream object at `offset`
+    fn stream_at(&mut self, offset: u64) -> Option<(u32, Vec<u8>, Vec<u8>)> {
+        let (num, dict, start) = self.object_at(offset)?;
+        let length = match value(&dict, b"/Length")? {
+            Value::Number(length) => length,
+            Value::Ref(length) => match value_at(&self.object(length)?, 0)? {
+                Value::Number(length) => length,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let length = u64::try_from(length).ok().filter(|length| *length <= MAX_STREAM_SIZE)?;
+        let raw = read_at(&mut self.reader, start?, length)?;
+        let data = decode_stream(&dict, &raw)?;
+        Some((num, dict, data))
+    }
+
+    // Number and body of the object at `offset`, with where the data starts if it's a stream
+    fn object_at(&mut self, offset: u64) -> Option<(u32, Vec<u8>, Option<u64>)> {
+        self.read_until(offset, |data| {
+            let Value::Number(num) = value_at(data, 0)? else { return None };
+            let header = find_all(data, b"obj").next()? + 3;
+            let body = &data[header..];
+            match (find_all(body, b"stream").next(), find_all(body, b"endobj").next()) {
+                (Some(stream), end) if body.len() > stream + 8 && end.is_none_or(|end| stream < end) => {
+                    // The data starts after the end of line following the keyword
+                    let mut start = stream + 6;
+                    if body[start] == b'\r' {
+                        start += 1;
+                    }
+                    if body[start] == b'\n' {
+                        start += 1;
+                    }
+                    Some((u32::try_from(num).ok()?, body[..stream].to_vec(), Some(offset + (header + start) as u64)))
+                }
+                (_, Some(end)) => Some((u32::try_from(num).ok()?, body[..end].to_vec(), None)),
+                _ => None,
+            }
+        })
+    }
+
+    // Reads from `offset` in growing chunks until `parse` finds what it's looking for
+    fn read_until<T>(&mut self, offset: u64, parse: impl Fn(&[u8]) -> Option<T>) -> Option<T> {
+        let mut size = 4096;
+        loop {
+            let data = read_at(&mut self.reader, offset, size)?;
+            if let Some(found) = parse(&data) {
+                return Some(found);
+            }
+            if (data.len() as u64) < size || size >= MAX_OBJECT_SIZE {
+                return None;
+            }
+            size *= 4;
+        }
+    }
+
+    // A text string from `dict`, long ones may be objects of their own
+    fn string(&mut self, dict: &[u8], key: &[u8]) -> Option<String> {
+        let text = match value(dict, key)? {
+            Value::String(s) => s,
+            Value::Ref(num) => match value_at(&self.object(num)?, 0)? {
+                Value::String(s) => s,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(text.trim().to_owned()).filter(|s| !s.is_empty())
+    }
+}
+
+// Inflates FlateDecode streams and undoes their PNG predictor, other filters aren't supported
+fn decode_stream(dict: &[u8], raw: &[u8]) -> Option<Vec<u8>> {
+    if find_all(dict, b"/Filter").next().is_none() {
+        return Some(raw.to_vec());
+    }
+    find_all(dict, b"/FlateDecode").next()?;
+    let mut inflated = vec![];
+    // Extra bytes after the compressed data are ignored by the decoder
+    if ZlibDecoder::new(raw).take(MAX_STREAM_SIZE).read_to_end(&mut inflated).is_err() && inflated.is_empty() {
+        return None;
+    }
+    match (value(dict, b"/Predictor"), value(dict, b"/Columns")) {
+        (Some(Value::Number(predictor)), columns) if predictor >= 10 => {
+            let columns = match columns {
+                Some(Value::Number(columns)) => usize::try_from(columns).ok().filter(|c| *c > 0)?,
+                _ => 1,
+            };
+            Some(unpredict(&inflated, columns))
+        }
+        _ => Some(inflated),
+    }
+}
+
+// Every row starts with the PNG filter it was encoded with, cross-reference streams mostly use "up"
+fn unpredict(data: &[u8], columns: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut previous = vec![0u8; columns];
+    for row in data.chunks_exact(columns + 1) {
+        let mut current = vec![0u8; columns];
+        for i in 0..columns {
+            let left = if i > 0 { current[i - 1] } else { 0 };
+            let up = previous[i];
+            let up_left = if i > 0 { previous[i - 1] } else { 0 };
+            let predicted = match row[0] {
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => {
+                    let estimate = left as i16 + up as i16 - up_left as i16;
+                    let distance = |b: u8| (estimate - b as i16).abs();
+                    if distance(left) <= distance(up) && distance(left) <= distance(up_left) {
+                        left
+                    } else if distance(up) <= distance(up_left) {
+                        up
+                    } else {
+                        up_left
+                    }
+                }
+                _ => 0,
+            };
+            current[i] = row[i + 1].wrapping_add(predicted);
+        }
+        out.extend_from_slice(&current);
+        previous = current;
+    }
+    out
+}
+
+fn find_all<'a>(data: &'a [u8], pattern: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+    data.windows(pattern.len()).enumerate().filter(move |(_, w)| *w == pattern).map(|(i, _)| i)
+}
+
+// The first "<<...>>" in `data`, with the dictionaries nested in it
+fn dictionary(data: &[u8]) -> Option<&[u8]> {
+    let start = find_all(data, b"<<").next()?;
+    let mut depth = 0;
+    let mut i = start;
+    while i + 1 < data.len() {
+        match &data[i..i + 2] {
+            b"<<" => {
+                depth += 1;
+                i += 2;
+            }
+            b">>" => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return Some(&data[start..i]);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+// The numbers of an array like "/W [1 2 1]"
+fn numbers(dict: &[u8], key: &[u8]) -> Option<Vec<u64>> {
+    let rest = &dict[key_end(dict, key)?..];
+    let rest = rest.trim_ascii_start().strip_prefix(b"[")?;
+    let end = rest.iter().position(|b| *b == b']')?;
+    String::from_utf8_lossy(&rest[..end]).split_whitespace().map(|n| n.parse().ok()).collect()
+}
+
+enum Value {
+    String(String),
+    Number(i64),
+    Ref(u32),
+    Other,
+}
+
+// The value following `key` in a dictionary
+fn value(dict: &[u8], key: &[u8]) -> Option<Value> {
+    value_at(dict, key_end(dict, key)?)
+}
+
+// Where the value of `key` starts, "/Type" doesn't match "/Types"
+fn key_end(dict: &[u8], key: &[u8]) -> Option<usize> {
+    let position = find_all(dict, key)
+        .find(|&i| dict.get(i + key.len()).is_none_or(|b| !b.is_ascii_alphanumeric()))?;
+    Some(position + key.len())
+}
+
+fn value_at(data: &[u8], start: usize) -> Option<Value> {
+    let mut i = start;
+    while data.get(i)?.is_ascii_whitespace() {
+        i += 1;
+    }
+    match data[i] {
+        b'(' => Some(Value::String(decode(&literal_string(&data[i + 1..])))),
+        b'<' if data.get(i + 1) != Some(&b'<') => {
+            let end = data[i..].iter().position(|b| *b == b'>')? + i;
+            let digits = data[i + 1..end].iter().filter(|b| b.is_ascii_hexdigit()).map(|b| *b as char).collect::<String>();
+            let bytes = (0..digits.len() / 2).filter_map(|j| u8::from_str_radix(&digits[j * 2..j * 2 + 2], 16).ok()).collect::<Vec<_>>();
+            Some(Value::String(decode(&bytes)))
+        }
+        b'0'..=b'9' | b'-' | b'+' => {
+            // "12 0 R" is a reference, "12" a number
+            let text = String::from_utf8_lossy(&data[i..(i + 32).min(data.len())]).to_string();
+            let mut words = text.split(|c: char| c.is_whitespace() || "/<>[]()".contains(c)).filter(|w| !w.is_empty());
+            let number = words.next()?.parse::<i64>().ok()?;
+            if words.next().is_some_and(|g| g.parse::<u32>().is_ok()) && words.next() == Some("R") {
+                return Some(Value::Ref(u32::try_from(number).ok()?));
+            }
+            Some(Value::Number(number))
+        }
+        _ => Some(Value::Other),
+    }
+}
+
+// The bytes of a "(...)" string, `data` starts after the opening parenthesis
+fn literal_string(data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut depth = 0;
+    let mut i = 0;
+    while let Some(&b) = data.get(i) {
+        i += 1;
+        match b {
+            b'\\' => {
+                let Some(&next) = data.get(i) else { break };
+                i += 1;
+                match next {
+                    b'n' => bytes.push(b'\n'),
+                    b'r' => bytes.push(b'\r'),
+                    b't' => bytes.push(b'\t'),
+                    b'b' => bytes.push(8),
+                    b'f' => bytes.push(12),
+                    b'0'..=b'7' => {
+                        let mut code = (next - b'0') as u32;
+                        for _ in 0..2 {
+                            match data.get(i) {
+                                Some(d @ b'0'..=b'7') => {
+                                    code = code * 8 + (d - b'0') as u32;
+                                    i += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        bytes.push(code as u8);
+                    }
+                    // A backslash at the end of a line continues the string
+                    b'\r' | b'\n' => {}
+                    other => bytes.push(other),
+                }
+            }
+            b'(' => {
+                depth += 1;
+                bytes.push(b);
+            }
+            b')' if depth == 0 => break,
+            b')' => {
+                depth -= 1;
+                bytes.push(b);
+            }
+            _ => bytes.push(b),
+        }
+    }
+    bytes
+}
+
+// Text strings are UTF-16 with a byte order mark or PDFDocEncoding, close enough to Latin-1
+fn decode(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(&[0xfe, 0xff]) {
+        let units = utf16.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]]));
+        return char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect();
+    }
+    if let Some(utf8) = bytes.strip_prefix(&[0xef, 0xbb, 0xbf]) {
+        return String::from_utf8_lossy(utf8).to_string();
+    }
+    bytes.iter().map(|b| *b as char).collect()
+}
+
+// "D:20240301140500+01'00'" to "2024-03-01 14:05:00"
+fn pdf_date(date: &str) -> String {
+    let digits = date.trim_start_matches("D:").chars().take_while(char::is_ascii_digit).collect::<String>();
+    if digits.len() < 8 {
+        return date.to_owned();
+    }
+    let part = |range: std::ops::Range<usize>| digits.get(range).unwrap_or("00");
+    format!("{}-{}-{} {}:{}:{}", part(0..4), part(4..6), part(6..8), part(8..10), part(10..12), part(12..14))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    // Numbers the objects from `first` and appends them with a cross-reference table for them
+    fn revision(file: &mut Vec<u8>, first: usize, objects: &[&str], trailer: &str) {
+        let offsets = objects.iter().enumerate().map(|(i, object)| {
+            let offset = file.len();
+            file.extend(format!("{} 0 obj\n{}\nendobj\n", first + i, object).bytes());
+            offset
+        }).collect::<Vec<_>>();
+        let xref = file.len();
+        file.extend(format!("xref\n{} {}\n", first, objects.len()).bytes());
+        for offset in offsets {
+            file.extend(format!("{:010} 00000 n\r\n", offset).bytes());
+        }
+        file.extend(format!("trailer\n<< {} >>\nstartxref\n{}\n%%EOF\n", trailer, xref).bytes());
+    }
+
+    fn simple_pdf() -> Vec<u8> {
+        let mut file = b"%PDF-1.4\n".to_vec();
+        revision(&mut file, 1, &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Count 3 /Kids [] >>",
+            "<< /Title (A \\(Test\\)) /Author 4 0 R /CreationDate (D:20240301140500+01'00') >>",
+            "<FEFF004100750074006800f6>",
+        ], "/Size 5 /Root 1 0 R /Info 3 0 R");
+        file
+    }
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn stream(num: usize, dict: &str, data: &[u8]) -> Vec<u8> {
+        [format!("{} 0 obj\n<< {} /Length {} >>\nstream\r\n", num, dict, data.len()).as_bytes(), data, b"\nendstream\nendobj\n"].concat()
+    }
+
+    #[test]
+    fn reads_the_info_dictionary_through_the_xref_table() {
+        let info = pdf(Cursor::new(simple_pdf())).unwrap();
+        assert_eq!(info.title.as_deref(), Some("A (Test)"));
+        assert_eq!(info.author.as_deref(), Some("Authö"));
+        assert_eq!(info.pages, Some(3));
+        assert_eq!(info.created.as_deref(), Some("2024-03-01 14:05:00"));
+    }
+
+    #[test]
+    fn newer_revisions_win() {
+        let mut file = simple_pdf();
+        let prev = String::from_utf8_lossy(&file).rfind("\nxref\n").unwrap() + 1;
+        revision(&mut file, 5, &["<< /Title (Edited) >>"], &format!("/Size 6 /Root 1 0 R /Info 5 0 R /Prev {}", prev));
+        let info = pdf(Cursor::new(file)).unwrap();
+        assert_eq!(info.title.as_deref(), Some("Edited"));
+        assert_eq!(info.pages, Some(3));
+    }
+
+    #[test]
+    fn reads_object_streams_through_an_xref_stream() {
+        let mut file = b"%PDF-1.5\n".to_vec();
+        let catalog = file.len();
+        file.extend(b"1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n");
+        let pages = file.len();
+        file.extend(b"2 0 obj\n<< /Type /Pages /Count 7 >>\nendobj\n");
+
+        // Object 3 has no author, the one of object 4 right after it mustn't be read as its own
+        let objects = b"<< /Title (Streamed) >> << /Author (Someone else) >>";
+        let body = [&b"3 0 4 24 "[..], objects].concat();
+        let object_stream = file.len();
+        file.extend(stream(5, "/Type /ObjStm /N 2 /First 9 /Filter /FlateDecode", &compress(&body)));
+
+        // Type, offset and index of objects 0 to 6, PNG "up" filtered with 4 columns
+        let xref = file.len();
+        let entries: [[u8; 4]; 7] = [
+            [0, 0, 0, 0],
+            [1, (catalog >> 8) as u8, catalog as u8, 0],
+            [1, (pages >> 8) as u8, pages as u8, 0],
+            [2, 0, 5, 0],
+            [2, 0, 5, 1],
+            [1, (object_stream >> 8) as u8, object_stream as u8, 0],
+            [1, (xref >> 8) as u8, xref as u8, 0],
+        ];
+        let mut rows = vec![];
+        let mut previous = [0; 4];
+        for entry in entries {
+            rows.push(2);
+            rows.extend(entry.iter().zip(previous).map(|(b, p)| b.wrapping_sub(p)));
+            previous = entry;
+        }
+        let dict = "/Type /XRef /Size 7 /W [1 2 1] /Root 1 0 R /Info 3 0 R /Filter /FlateDecode /DecodeParms << /Columns 4 /Predictor 12 >>";
+        file.extend(stream(6, dict, &compress(&rows)));
+        file.extend(format!("startxref\n{}\n%%EOF\n", xref).bytes());
+
+        let info = pdf(Cursor::new(file)).unwrap();
+        assert_eq!(info.title.as_deref(), Some("Streamed"));
+        assert_eq!(info.author, None);
+        assert_eq!(info.pages, Some(7));
+    }
+
+    #[test]
+    fn broken_files_do_not_panic() {
+        let file = simple_pdf();
+        for len in 0..file.len() {
+            pdf(Cursor::new(&file[..len]));
+        }
+        // A section pointing back to itself
+        let mut file = simple_pdf();
+        let xref = String::from_utf8_lossy(&file).rfind("\nxref\n").unwrap() + 1;
+        let trailer = format!("/Size 5 /Root 1 0 R /Prev {}", xref);
+        file = String::from_utf8_lossy(&file).replacen("/Size 5 /Root 1 0 R", &trailer, 1).into_bytes();
+        pdf(Cursor::new(file));
+        assert!(pdf(Cursor::new(b"%PDF-1.4\nstartxref\n99999999999999999999\n%%EOF")).is_none());
+        assert!(pdf(Cursor::new(b"not a pdf")).is_none());
+    }
+
+    #[test]
+    fn object_stream_offsets_that_overflow_are_ignored() {
+        let mut pdf = Pdf { reader: Cursor::new(vec![]), objects: HashMap::new(), trailers: vec![], streams: HashMap::new() };
+        let header = format!("3 {} ", usize::MAX);
+        let file = [b"%PDF-1.5\n".to_vec(), stream(5, &format!("/Type /ObjStm /N 1 /First {}", header.len()), header.as_bytes())].concat();
+        pdf.reader = Cursor::new(file);
+        pdf.objects.insert(5, Location::Offset(9));
+        pdf.objects.insert(3, Location::Compressed(5));
+        assert_eq!(pdf.object(3), None);
+    }
+
+    #[test]
+    fn encrypted_strings_are_not_read() {
+        let mut file = b"%PDF-1.4\n".to_vec();
+        revision(&mut file, 1, &["<< /Title (Garbled) >>"], "/Size 2 /Info 1 0 R /Encrypt << /Filter /Standard >>");
+        assert_eq!(pdf(Cursor::new(file)).unwrap().title, None);
+    }
+
+    #[test]
+    fn unpredicts_png_rows() {
+        assert_eq!(unpredict(&[2, 1, 2, 2, 1, 1, 1, 5, 0], 2), vec![1, 2, 2, 3, 5, 5]);
+        assert_eq!(unpredict(&[1, 1, 1, 9], 2), vec![1, 2]);
+        assert!(unpredict(&[2, 1], 2).is_empty());
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use iced::futures::channel::oneshot;
+use iced::Task;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Copy,
+    Move,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Job {
+    pub operation: Operation,
+    pub src: PathBuf,
+    pub dest_dir: PathBuf,
+}
+
+// Files that were copied or cut and are waiting to be pasted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clipboard {
+    pub operation: Operation,
+    pub paths: Vec<PathBuf>,
+}
+
+// Runs jobs one at a time so two transfers never fight over the same files
+#[derive(Debug, Default)]
+pub struct Queue {
+    pending: VecDeque<Job>,
+    running: Option<Job>,
+}
+
+impl Queue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns the job to start if nothing was running
+    pub fn push(&mut self, job: Job) -> Option<Job> {
+        self.pending.push_back(job);
+        if self.running.is_none() { self.next() } else { None }
+    }
+
+    // Marks the running job as done and returns it along with the job to start next
+    pub fn finish(&mut self) -> (Option<Job>, Option<Job>) {
+        let finished = self.running.take();
+        (finished, self.next())
+    }
+
+    fn next(&mut self) -> Option<Job> {
+        self.running = self.pending.pop_front();
+        self.running.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len() + self.running.iter().count()
+    }
+}
+
+// Copies a file or a whole directory into `dest_dir`, keeping its name
+pub fn copy_into(src: &Path, dest_dir: &Path) -> io::Result<PathBuf> {
+    let dest = target(src, dest_dir)?;
+    copy_recursive(src, &dest)?;
+    Ok(dest)
+}
+
+// Moves a file or directory into `dest_dir`, copying when it lives on another filesystem.
+// Any other rename error is returned as is, copying then would leave duplicates behind
+pub fn move_into(src: &Path, dest_dir: &Path) -> io::Result<PathBuf> {
+    let dest = target(src, dest_dir)?;
+    match std::fs::rename(src, &dest) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            copy_recursive(src, &dest)?;
+            if std::fs::symlink_metadata(src)?.is_dir() {
+                std::fs::remove_dir_all(src)?;
+            } else {
+                std::fs::remove_file(src)?;
+            }
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(dest)
+}
+
+fn target(src: &Path, dest_dir: &Path) -> io::Result<PathBuf> {
+    let name = src.file_name().ok_or(io::Error::new(io::ErrorKind::InvalidInput, "path has no name"))?;
+    let dest = dest_dir.join(name);
+    if dest.exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", dest.to_string_lossy())));
+    }
+    if dest.starts_with(src) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "can't copy a directory into itself"));
+    }
+    Ok(dest)
+}
+
+fn copy_recursive(src: &Path, dest: &Path) -> io::Result<()> {
+    let metadata = std::fs::symlink_metadata(src)?;
+    if metadata.is_dir() {
+        std::fs::create_dir(dest)?;
+        for entry in std::fs::read_dir(src)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+        std::fs::set_permissions(dest, metadata.permissions())?;
+    } else if metadata.is_symlink() {
+        // The link itself is recreated, copying would follow it
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(std::fs::read_link(src)?, dest)?;
+        #[cfg(not(unix))]
+        std::fs::copy(src, dest)?;
+    } else {
+        std::fs::copy(src, dest)?;
+    }
+    Ok(())
+}
+
+// Runs the job on its own thread so long copies don't hold up the rayon pool,
+// the result holds the error message if it failed
+pub fn run(job: Job) -> Task<Result<PathBuf, String>> {
+    let (sender, receiver) = oneshot::channel();
+
+    std::thread::spawn(move || {
+        let result = match job.operation {
+            Operation::Copy => copy_into(&job.src, &job.dest_dir),
+            Operation::Move => move_into(&job.src, &job.dest_dir),
+        };
+        let _ = sender.send(result.map_err(|e| e.to_string()));
+    });
+
+    Task::perform(receiver, |result| result.unwrap_or(Err("operation was cancelled".to_owned())))
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::components::fileitem::{FileData, FileType};
+use crate::utils::date_bucket;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GroupBy {
+    #[default]
+    None,
+    FileType,
+    LastModified,
+    Size,
+}
+
+impl GroupBy {
+    pub const ALL: [GroupBy; 4] = [
+        GroupBy::None,
+        GroupBy::FileType,
+        GroupBy::LastModified,
+        GroupBy::Size,
+    ];
+}
+
+impl fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            GroupBy::None => "No grouping",
+            GroupBy::FileType => "Group by type",
+            GroupBy::LastModified => "Group by date",
+            GroupBy::Size => "Group by size",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub label: String,
+    pub items: Vec<FileData>,
+    pub total_size: u64,
+    order: u8,
+}
+
+fn type_bucket(data: &FileData) -> (u8, String) {
+    let file_type = data.file_type.clone().unwrap_or(FileType::Unknown);
+    let label = match file_type {
+        FileType::Directory => "Folders",
+        FileType::File => "Files",
+        FileType::Image => "Images",
+        FileType::Video => "Videos",
+        FileType::Audio => "Audio",
+        FileType::Document => "Documents",
+        FileType::Unknown => "Other",
+    };
+    (file_type as u8, label.to_string())
+}
+
+fn size_bucket(data: &FileData) -> (u8, String) {
+    const KB: u64 = 1024;
+    const MB: u64 = 1024 * KB;
+    const GB: u64 = 1024 * MB;
+
+    if data.is_dir {
+        return (0, "Folders".to_string());
+    }
+    // Biggest first so the files worth cleaning up are on top
+    let (order, label) = match data.size {
+        None => (u8::MAX, "Unknown"),
+        Some(0) => (6, "Empty"),
+        Some(s) if s < 16 * KB => (5, "Tiny (< 16 KB)"),
+        Some(s) if s < MB => (4, "Small (< 1 MB)"),
+        Some(s) if s < 128 * MB => (3, "Medium (< 128 MB)"),
+        Some(s) if s < GB => (2, "Large (< 1 GB)"),
+        Some(_) => (1, "Huge (> 1 GB)"),
+    };
+    (order, label.to_string())
+}
+
+// Splits an already sorted tree into groups, keeping the order inside each group
+pub fn group_files(tree: &[FileData], by: GroupBy) -> Vec<Group> {
+    let mut groups: Vec<Group> = vec![];
+
+    for data in tree {
+        let (order, label) = match by {
+            GroupBy::None => (0, "".to_string()),
+            GroupBy::FileType => type_bucket(data),
+            GroupBy::LastModified => date_bucket(data.last_modified),
+            GroupBy::Size => size_bucket(data),
+        };
+
+        let group = match groups.iter().position(|g| g.label == label) {
+            Some(i) => &mut groups[i],
+            None => {
+                groups.push(Group { label, items: vec![], total_size: 0, order });
+                groups.last_mut().unwrap()
+            }
+        };
+        group.total_size += data.size.unwrap_or(0);
+        group.items.push(data.clone());
+    }
+
+    groups.sort_by_key(|g| g.order);
+    groups
+}
@@ -0,0 +1,136 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use iced::futures::channel::oneshot;
+use iced::Task;
+use memmap2::Mmap;
+
+// Kept narrow so a row fits in the preview pane
+pub const BYTES_PER_ROW: u64 = 8;
+pub const ROWS: u64 = 48;
+pub const PAGE_SIZE: u64 = BYTES_PER_ROW * ROWS;
+
+// Searches check for cancellation after every chunk
+const SEARCH_CHUNK: usize = 4 * 1024 * 1024;
+
+// Maps the file instead of reading it, only the pages that are looked at get loaded.
+// Only for the hex view: if another program truncates the file while it's mapped, reading
+// the pages that are gone raises SIGBUS and takes the whole app down
+pub fn map(path: &Path) -> Option<Arc<Mmap>> {
+    let file = std::fs::File::open(path).ok()?;
+    if file.metadata().ok()?.len() == 0 {
+        return None;
+    }
+    let map = unsafe { Mmap::map(&file) }.ok()?;
+    Some(Arc::new(map))
+}
+
+// Aligns `offset` to a row and keeps the last page full
+pub fn clamp_offset(offset: i64, len: u64) -> u64 {
+    let last_row = len.saturating_sub(1) / BYTES_PER_ROW * BYTES_PER_ROW;
+    let last_page = last_row.saturating_sub((ROWS - 1) * BYTES_PER_ROW);
+    (offset.max(0) as u64 / BYTES_PER_ROW * BYTES_PER_ROW).min(last_page)
+}
+
+// "00000010  48 65 6c 6c 6f 0a 00 01  Hello..."
+pub fn row(bytes: &[u8], offset: u64) -> String {
+    let mut line = format!("{:08x} ", offset);
+    for i in 0..BYTES_PER_ROW as usize {
+        match bytes.get(i) {
+            Some(byte) => line.push_str(&format!(" {:02x}", byte)),
+            None => line.push_str("   "),
+        }
+    }
+    line.push_str("  ");
+    line.extend(bytes.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }));
+    line
+}
+
+// Decimal, or hex with a "0x" prefix
+pub fn parse_offset(input: &str) -> Option<u64> {
+    let input = input.trim();
+    match input.strip_prefix("0x").or(input.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => input.parse().ok(),
+    }
+}
+
+// Hex pairs like "de ad be ef", anything else is searched for as text
+pub fn parse_pattern(input: &str) -> Option<Vec<u8>> {
+    if input.is_empty() {
+        return None;
+    }
+    let digits = input.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    if digits.is_empty() {
+        return None;
+    }
+    let is_hex = digits.len() % 2 == 0 && digits.chars().all(|c| c.is_ascii_hexdigit())
+        && input.split_whitespace().all(|word| word.len() % 2 == 0);
+    if is_hex {
+        (0..digits.len()).step_by(2).map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok()).collect()
+    } else {
+        Some(input.as_bytes().to_vec())
+    }
+}
+
+// Finds the next occurrence of `pattern` after `from`, wrapping around at the end
+pub fn search(map: Arc<Mmap>, pattern: Vec<u8>, from: u64, cancelled: Arc<AtomicBool>) -> Task<Option<u64>> {
+    let (sender, receiver) = oneshot::channel();
+
+    rayon::spawn(move || {
+        let from = (from as usize).min(map.len());
+        let found = find(&map, &pattern, from, map.len(), &cancelled)
+            .or_else(|| find(&map, &pattern, 0, (from + pattern.len()).min(map.len()), &cancelled));
+        let _ = sender.send(found.map(|pos| pos as u64));
+    });
+
+    Task::perform(receiver, |result| result.ok().flatten())
+}
+
+fn find(bytes: &[u8], pattern: &[u8], start: usize, end: usize, cancelled: &AtomicBool) -> Option<usize> {
+    if pattern.is_empty() {
+        return None;
+    }
+    let mut chunk = start;
+    while chunk < end {
+        if cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        // Chunks overlap by the pattern length so matches across the border are found
+        let chunk_end = (chunk + SEARCH_CHUNK + pattern.len() - 1).min(end);
+        if let Some(pos) = bytes[chunk..chunk_end].windows(pattern.len()).position(|w| w == pattern) {
+            return Some(chunk + pos);
+        }
+        chunk += SEARCH_CHUNK;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_pairs_and_text() {
+        assert_eq!(parse_pattern("de ad BE ef"), Some(vec![0xde, 0xad, 0xbe, 0xef]));
+        assert_eq!(parse_pattern("cafe"), Some(vec![0xca, 0xfe]));
+        assert_eq!(parse_pattern("abc"), Some(b"abc".to_vec()));
+        assert_eq!(parse_pattern("a bc d"), Some(b"a bc d".to_vec()));
+    }
+
+    #[test]
+    fn empty_patterns_are_not_searched() {
+        assert_eq!(parse_pattern(""), None);
+        assert_eq!(parse_pattern("   "), None);
+        assert_eq!(find(b"data", b"", 0, 4, &AtomicBool::new(false)), None);
+    }
+
+    #[test]
+    fn finds_matches_across_chunks() {
+        let mut data = vec![0; SEARCH_CHUNK + 2];
+        data[SEARCH_CHUNK - 1..SEARCH_CHUNK + 1].copy_from_slice(b"hi");
+        assert_eq!(find(&data, b"hi", 0, data.len(), &AtomicBool::new(false)), Some(SEARCH_CHUNK - 1));
+        assert_eq!(find(&data, b"hi", 0, data.len(), &AtomicBool::new(true)), None);
+    }
+}
@@ -0,0 +1,387 @@
+use std::path::Path;
+
+// What a piece of source code is, the view picks the colour from the app theme
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token {
+    Plain,
+    Keyword,
+    Type,
+    String,
+    Number,
+    Comment,
+}
+
+pub struct Language {
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    // Interpreters that select this language in a `#!` line
+    interpreters: &'static [&'static str],
+    keywords: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+}
+
+const C_KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else", "enum", "extern",
+    "float", "for", "goto", "if", "inline", "int", "long", "register", "return", "short", "signed", "sizeof",
+    "static", "struct", "switch", "typedef", "union", "unsigned", "void", "volatile", "while", "bool", "true",
+    "false", "NULL", "class", "namespace", "template", "typename", "public", "private", "protected", "virtual",
+    "override", "new", "delete", "this", "using", "nullptr", "constexpr", "try", "catch", "throw",
+];
+
+const JS_KEYWORDS: &[&str] = &[
+    "async", "await", "break", "case", "catch", "class", "const", "continue", "debugger", "default", "delete",
+    "do", "else", "export", "extends", "false", "finally", "for", "from", "function", "if", "import", "in",
+    "instanceof", "let", "new", "null", "of", "return", "static", "super", "switch", "this", "throw", "true",
+    "try", "typeof", "undefined", "var", "void", "while", "yield", "interface", "type", "enum", "implements",
+    "readonly", "abstract", "as", "any", "number", "string", "boolean",
+];
+
+const SHELL_KEYWORDS: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "case", "esac", "for", "while", "until", "do", "done", "in", "function",
+    "return", "local", "export", "readonly", "exit", "echo", "source", "set", "unset", "shift", "break", "continue",
+];
+
+pub const LANGUAGES: &[Language] = &[
+    Language {
+        name: "Rust",
+        extensions: &["rs"],
+        interpreters: &[],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false",
+            "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+            "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+    },
+    Language {
+        name: "C/C++",
+        extensions: &["c", "h", "cc", "cpp", "cxx", "hh", "hpp", "hxx", "m", "mm"],
+        interpreters: &[],
+        keywords: C_KEYWORDS,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+    },
+    Language {
+        name: "C#",
+        extensions: &["cs"],
+        interpreters: &[],
+        keywords: &[
+            "abstract", "as", "base", "bool", "break", "case", "catch", "class", "const", "continue", "default",
+            "do", "else", "enum", "false", "finally", "for", "foreach", "if", "in", "int", "interface", "internal",
+            "is", "namespace", "new", "null", "out", "override", "private", "protected", "public", "readonly", "ref",
+            "return", "sealed", "static", "string", "struct", "switch", "this", "throw", "true", "try", "using",
+            "var", "virtual", "void", "while", "async", "await",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+    },
+    Language {
+        name: "Java",
+        extensions: &["java", "kt", "kts", "scala", "groovy", "gradle"],
+        interpreters: &[],
+        keywords: &[
+            "abstract", "boolean", "break", "case", "catch", "class", "continue", "default", "do", "double", "else",
+            "enum", "extends", "false", "final", "finally", "float", "for", "if", "implements", "import", "int",
+            "interface", "long", "new", "null", "package", "private", "protected", "public", "return", "static",
+            "super", "switch", "this", "throw", "throws", "true", "try", "void", "while", "fun", "val", "var",
+            "object", "when", "def",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+    },
+    Language {
+        name: "Go",
+        extensions: &["go"],
+        interpreters: &[],
+        keywords: &[
+            "break", "case", "chan", "const", "continue", "default", "defer", "else", "fallthrough", "for", "func",
+            "go", "goto", "if", "import", "interface", "map", "package", "range", "return", "select", "struct",
+            "switch", "type", "var", "nil", "true", "false",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+    },
+    Language {
+        name: "JavaScript",
+        extensions: &["js", "mjs", "cjs", "jsx", "ts", "tsx"],
+        interpreters: &["node", "deno", "bun"],
+        keywords: JS_KEYWORDS,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+    },
+    Language {
+        name: "Python",
+        extensions: &["py", "pyw", "pyi"],
+        interpreters: &["python"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif", "else",
+            "except", "False", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "None",
+            "nonlocal", "not", "or", "pass", "raise", "return", "True", "try", "while", "with", "yield", "self",
+        ],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Language {
+        name: "Ruby",
+        extensions: &["rb", "rake", "gemspec"],
+        interpreters: &["ruby"],
+        keywords: &[
+            "alias", "and", "begin", "break", "case", "class", "def", "do", "else", "elsif", "end", "ensure",
+            "false", "for", "if", "in", "module", "next", "nil", "not", "or", "redo", "rescue", "retry", "return",
+            "self", "super", "then", "true", "undef", "unless", "until", "when", "while", "yield", "require",
+        ],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Language {
+        name: "Perl",
+        extensions: &["pl", "pm"],
+        interpreters: &["perl"],
+        keywords: &["my", "our", "sub", "if", "elsif", "else", "unless", "while", "for", "foreach", "return", "use", "package"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Language {
+        name: "Shell",
+        extensions: &["sh", "bash", "zsh", "fish", "ksh"],
+        interpreters: &["sh", "bash", "zsh", "fish", "ksh", "dash"],
+        keywords: SHELL_KEYWORDS,
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Language {
+        name: "Lua",
+        extensions: &["lua"],
+        interpreters: &["lua"],
+        keywords: &[
+            "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in", "local",
+            "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+        ],
+        line_comments: &["--"],
+        block_comment: Some(("--[[", "]]")),
+        quotes: &['"', '\''],
+    },
+    Language {
+        name: "PHP",
+        extensions: &["php"],
+        interpreters: &["php"],
+        keywords: &[
+            "abstract", "array", "as", "break", "case", "catch", "class", "const", "continue", "default", "do",
+            "echo", "else", "elseif", "extends", "false", "final", "for", "foreach", "function", "if", "implements",
+            "interface", "namespace", "new", "null", "private", "protected", "public", "return", "static", "switch",
+            "this", "throw", "true", "try", "use", "while",
+        ],
+        line_comments: &["//", "#"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+    },
+    Language {
+        name: "Swift",
+        extensions: &["swift"],
+        interpreters: &["swift"],
+        keywords: &[
+            "as", "break", "case", "class", "continue", "default", "defer", "do", "else", "enum", "extension",
+            "false", "for", "func", "guard", "if", "import", "in", "init", "let", "nil", "private", "protocol",
+            "public", "return", "self", "static", "struct", "switch", "throw", "true", "try", "var", "where", "while",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+    },
+    Language {
+        name: "Haskell",
+        extensions: &["hs"],
+        interpreters: &["runhaskell"],
+        keywords: &[
+            "case", "class", "data", "deriving", "do", "else", "if", "import", "in", "instance", "let", "module",
+            "newtype", "of", "then", "type", "where",
+        ],
+        line_comments: &["--"],
+        block_comment: Some(("{-", "-}")),
+        quotes: &['"'],
+    },
+    Language {
+        name: "SQL",
+        extensions: &["sql"],
+        interpreters: &[],
+        keywords: &[
+            "select", "from", "where", "insert", "into", "values", "update", "set", "delete", "create", "table",
+            "drop", "alter", "index", "join", "left", "right", "inner", "outer", "on", "group", "by", "order",
+            "having", "limit", "and", "or", "not", "null", "as", "primary", "key", "SELECT", "FROM", "WHERE",
+            "INSERT", "INTO", "VALUES", "UPDATE", "SET", "DELETE", "CREATE", "TABLE", "DROP", "ALTER", "INDEX",
+            "JOIN", "LEFT", "RIGHT", "INNER", "OUTER", "ON", "GROUP", "BY", "ORDER", "HAVING", "LIMIT", "AND",
+            "OR", "NOT", "NULL", "AS", "PRIMARY", "KEY",
+        ],
+        line_comments: &["--"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+    },
+    Language {
+        name: "HTML/XML",
+        extensions: &["html", "htm", "xml", "svg", "xhtml", "vue"],
+        interpreters: &[],
+        keywords: &[],
+        line_comments: &[],
+        block_comment: Some(("<!--", "-->")),
+        quotes: &['"', '\''],
+    },
+    Language {
+        name: "CSS",
+        extensions: &["css", "scss", "sass", "less"],
+        interpreters: &[],
+        keywords: &["important", "media", "import", "from", "to", "and"],
+        line_comments: &[],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+    },
+    Language {
+        name: "Config",
+        extensions: &["toml", "ini", "cfg", "conf", "yaml", "yml", "json", "env", "properties"],
+        interpreters: &[],
+        keywords: &["true", "false", "null", "yes", "no", "on", "off"],
+        line_comments: &["#", ";"],
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Language {
+        name: "Makefile",
+        extensions: &["mk", "cmake"],
+        interpreters: &["make"],
+        keywords: &["ifeq", "ifneq", "ifdef", "ifndef", "else", "endif", "include", "define", "endef", "export"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+];
+
+pub fn is_source(extension: &str) -> bool {
+    LANGUAGES.iter().any(|l| l.extensions.contains(&extension))
+}
+
+// Picks the language from the extension, or from a `#!` line for scripts without one
+pub fn detect(path: &Path, text: &str) -> Option<&'static Language> {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    if name == "Makefile" || name == "makefile" || name == "GNUmakefile" {
+        return LANGUAGES.iter().find(|l| l.name == "Makefile");
+    }
+    if let Some(language) = LANGUAGES.iter().find(|l| l.extensions.contains(&extension.as_str())) {
+        return Some(language);
+    }
+
+    let interpreter = shebang(text.lines().next()?)?;
+    LANGUAGES.iter().find(|l| l.interpreters.contains(&interpreter.as_str()))
+}
+
+// The info string of a fenced code block, like "rust", "py" or "bash"
+pub fn by_name(name: &str) -> Option<&'static Language> {
+    let name = name.split_whitespace().next()?.to_lowercase();
+    LANGUAGES.iter().find(|l| {
+        l.name.to_lowercase() == name || l.extensions.contains(&name.as_str()) || l.interpreters.contains(&name.as_str())
+    })
+}
+
+// "#!/usr/bin/env -S python3.11 -u" gives "python"
+fn shebang(line: &str) -> Option<String> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+    }
+    Some(program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.').to_owned())
+}
+
+// Splits every line into tokens, block comments can span several lines
+pub fn highlight<'a>(lines: impl Iterator<Item = &'a str>, language: &Language) -> Vec<Vec<(String, Token)>> {
+    let mut in_comment = false;
+    lines.map(|line| highlight_line(line, language, &mut in_comment)).collect()
+}
+
+fn highlight_line(line: &str, language: &Language, in_comment: &mut bool) -> Vec<(String, Token)> {
+    let mut spans: Vec<(String, Token)> = vec![];
+    let mut push = |text: &str, token: Token| {
+        match spans.last_mut() {
+            Some((last, last_token)) if *last_token == token => last.push_str(text),
+            _ => spans.push((text.to_owned(), token)),
+        }
+    };
+
+    let mut i = 0;
+    while i < line.len() {
+        let rest = &line[i..];
+
+        if *in_comment {
+            let end = language.block_comment.map(|(_, end)| end).unwrap_or_default();
+            match rest.find(end) {
+                Some(pos) if !end.is_empty() => {
+                    push(&rest[..pos + end.len()], Token::Comment);
+                    i += pos + end.len();
+                    *in_comment = false;
+                }
+                _ => {
+                    push(rest, Token::Comment);
+                    break;
+                }
+            }
+            continue;
+        }
+        if let Some((start, _)) = language.block_comment.filter(|(start, _)| rest.starts_with(start)) {
+            push(start, Token::Comment);
+            i += start.len();
+            *in_comment = true;
+            continue;
+        }
+        if language.line_comments.iter().any(|c| rest.starts_with(c)) {
+            push(rest, Token::Comment);
+            break;
+        }
+
+        let c = rest.chars().next().unwrap_or_default();
+        let len = if language.quotes.contains(&c) {
+            // Up to the closing quote, skipping escaped ones
+            let mut escaped = false;
+            rest.char_indices().skip(1)
+                .find(|&(_, ch)| {
+                    let end = ch == c && !escaped;
+                    escaped = ch == '\\' && !escaped;
+                    end
+                })
+                .map(|(pos, ch)| pos + ch.len_utf8())
+                .unwrap_or(rest.len())
+        } else if c.is_alphanumeric() || c == '_' {
+            rest.find(|ch: char| !(ch.is_alphanumeric() || ch == '_' || (c.is_ascii_digit() && ch == '.')))
+                .unwrap_or(rest.len())
+        } else {
+            c.len_utf8()
+        };
+
+        let word = &rest[..len];
+        let token = if language.quotes.contains(&c) {
+            Token::String
+        } else if c.is_ascii_digit() {
+            Token::Number
+        } else if language.keywords.contains(&word) {
+            Token::Keyword
+        } else if c.is_uppercase() {
+            Token::Type
+        } else {
+            Token::Plain
+        };
+        push(word, token);
+        i += len;
+    }
+    spans
+}
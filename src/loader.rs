@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use iced::futures::channel::mpsc;
+use iced::Task;
+use rayon::prelude::*;
+
+use crate::components::fileitem::FileData;
+use crate::sorting::{sort_files, Sort};
+
+// How many entries are sent to the view at a time
+const CHUNK_SIZE: usize = 256;
+
+// Load ids are unique across loaders so events can't be mistaken for another loader's
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone)]
+pub enum LoadEvent {
+    // Names only, metadata is still missing
+    Entries(u64, Vec<FileData>),
+    // Entries which had their metadata filled in
+    Metadata(u64, Vec<FileData>),
+    Finished(u64),
+}
+
+impl LoadEvent {
+    pub fn id(&self) -> u64 {
+        match self {
+            LoadEvent::Entries(id, _) => *id,
+            LoadEvent::Metadata(id, _) => *id,
+            LoadEvent::Finished(id) => *id,
+        }
+    }
+}
+
+// What a load reads besides the names and basic metadata of the entries
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LoadOptions {
+    // Also count the entries of every directory, this reads each of them
+    pub count_items: bool,
+    // Also read when photos were taken, this opens each image
+    pub read_taken: bool,
+    // Also read the tags of audio files
+    pub read_tags: bool,
+    // Also read the container headers of videos
+    pub read_video: bool,
+    // Also read the metadata of documents
+    pub read_documents: bool,
+    // Skip everything but directories, the skipped entries are never stat'ed
+    pub dirs_only: bool,
+}
+
+// Keeps track of the directory load that is currently running so a stale one can be dropped
+#[derive(Debug, Default)]
+pub struct Loader {
+    id: u64,
+    cancelled: Option<Arc<AtomicBool>>,
+    pub options: LoadOptions,
+}
+
+impl Loader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Cancels the previous load (if any) and starts reading `path`
+    pub fn start(&mut self, path: PathBuf) -> Task<LoadEvent> {
+        self.cancel();
+        self.id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.cancelled = Some(cancelled.clone());
+
+        load_dir(path, self.id, cancelled, self.options)
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some(cancelled) = self.cancelled.take() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // Whether the event belongs to the load that is currently running
+    pub fn is_current(&self, event: &LoadEvent) -> bool {
+        self.cancelled.is_some() && event.id() == self.id
+    }
+
+    pub fn finish(&mut self) {
+        self.cancelled = None;
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.cancelled.is_some()
+    }
+}
+
+// A listing that is thrown away, like a truncated column, stops reading its directory
+impl Drop for Loader {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+// The entries of a directory other than the current one, kept sorted as they stream in
+pub struct Listing {
+    pub path: PathBuf,
+    pub tree: Vec<FileData>,
+    loader: Loader,
+}
+
+impl Listing {
+    pub fn open(path: PathBuf) -> (Self, Task<LoadEvent>) {
+        Self::start(path, Loader::new())
+    }
+
+    // Lists the sub directories only, like the folder tree shows
+    pub fn open_dirs(path: PathBuf) -> (Self, Task<LoadEvent>) {
+        let mut loader = Loader::new();
+        loader.options.dirs_only = true;
+        Self::start(path, loader)
+    }
+
+    fn start(path: PathBuf, mut loader: Loader) -> (Self, Task<LoadEvent>) {
+        let task = loader.start(path.clone());
+        (
+            Self {
+                path,
+                tree: vec![],
+                loader,
+            },
+            task,
+        )
+    }
+
+    pub fn is_current(&self, event: &LoadEvent) -> bool {
+        self.loader.is_current(event)
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loader.is_loading()
+    }
+
+    pub fn apply(&mut self, event: LoadEvent, sort: &Sort, folders_first: bool) {
+        match event {
+            LoadEvent::Entries(_, entries) => {
+                self.tree.extend(entries);
+                sort_files(&mut self.tree, sort, folders_first);
+            }
+            LoadEvent::Metadata(_, entries) => {
+                let mut filled = entries.into_iter()
+                    .map(|e| (e.path.clone(), e))
+                    .collect::<HashMap<_, _>>();
+                for item in self.tree.iter_mut() {
+                    if let Some(data) = filled.remove(&item.path) {
+                        *item = data;
+                    }
+                }
+            }
+            LoadEvent::Finished(_) => {
+                sort_files(&mut self.tree, sort, folders_first);
+                self.loader.finish();
+            }
+        }
+    }
+}
+
+fn load_dir(path: PathBuf, id: u64, cancelled: Arc<AtomicBool>, options: LoadOptions) -> Task<LoadEvent> {
+    let (sender, receiver) = mpsc::unbounded();
+
+    std::thread::spawn(move || {
+        let mut entries = vec![];
+        let mut chunk = vec![];
+
+        for entry in std::fs::read_dir(&path).into_iter().flatten().filter_map(Result::ok) {
+            if cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+            // `file_type` comes from the dirent on most platforms so this does not stat
+            let is_dir = match entry.file_type() {
+                Ok(t) if t.is_symlink() => entry.path().is_dir(),
+                Ok(t) => t.is_dir(),
+                Err(_) => false,
+            };
+            if options.dirs_only && !is_dir {
+                continue;
+            }
+            chunk.push(FileData::lazy(entry.path(), is_dir));
+
+            if chunk.len() >= CHUNK_SIZE {
+                entries.extend(chunk.iter().cloned());
+                if sender.unbounded_send(LoadEvent::Entries(id, std::mem::take(&mut chunk))).is_err() {
+                    return;
+                }
+            }
+        }
+        if !chunk.is_empty() {
+            entries.extend(chunk.iter().cloned());
+            let _ = sender.unbounded_send(LoadEvent::Entries(id, chunk));
+        }
+
+        entries.par_chunks(CHUNK_SIZE).for_each(|chunk| {
+            if cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+            let filled = chunk.iter()
+                .cloned()
+                .map(FileData::with_metadata)
+                .map(|data| if options.count_items { data.with_item_count() } else { data })
+                .map(|data| if options.read_taken { data.with_taken() } else { data })
+                .map(|data| if options.read_tags { data.with_tags() } else { data })
+                .map(|data| if options.read_video { data.with_video() } else { data })
+                .map(|data| if options.read_documents { data.with_document() } else { data })
+                .collect::<Vec<_>>();
+            let _ = sender.unbounded_send(LoadEvent::Metadata(id, filled));
+        });
+
+        if !cancelled.load(Ordering::Relaxed) {
+            let _ = sender.unbounded_send(LoadEvent::Finished(id));
+        }
+    });
+
+    Task::run(receiver, |event| event)
+}
@@ -2,16 +2,45 @@ mod platform;
 mod views;
 mod utils;
 mod components;
+mod fileops;
+mod audio;
+mod columns;
+mod document;
+mod grouping;
+mod hex;
+mod highlight;
+mod loader;
+mod markdown;
+mod mp4;
+mod photo;
+mod preview;
+mod search;
+mod session;
+mod settings;
+mod sorting;
+mod structured;
+mod tabular;
+mod thumbnails;
+mod typeface;
+mod video;
+mod zip;
 
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use iced::advanced::mouse;
 use iced::font::Font;
-use iced::widget::{row, text};
-use iced::{Element, Subscription, Task, Theme};
+use iced::widget::{button, column, container, row, text};
+use iced::{event, window, Element, Event, Length, Padding, Point, Rectangle, Size, Subscription, Task, Theme};
 
-use crate::views::explorer;
+use crate::fileops::{Clipboard, Job, Operation, Queue};
+use crate::session::{Session, WindowState};
+use crate::settings::Settings;
+use crate::views::{explorer, tabs};
 use crate::views::View;
 
 pub fn main() -> iced::Result {
-    iced::application(Filed::new, Filed::update, Filed::view)
+    iced::daemon(Filed::new, Filed::update, Filed::view)
         .subscription(Filed::subscription)
         .title(Filed::title)
         .theme(Filed::theme)
@@ -27,76 +56,341 @@ pub fn main() -> iced::Result {
 
 #[derive(Debug, Clone)]
 enum Message {
-    Loaded,
-    Explorer(explorer::Message),
+    WindowOpened(window::Id, WindowState),
+    WindowClosed(window::Id),
+    Event(window::Id, Event, event::Status),
+    Tabs(window::Id, tabs::Message),
+    OperationFinished(Result<PathBuf, String>),
+    DismissFailure(usize),
 }
 
 struct Filed {
-    view: View,
+    windows: BTreeMap<window::Id, View>,
+
+    // Shared by every window
+    clipboard: Option<Clipboard>,
+    queue: Queue,
+    // Operations that failed, shown at the bottom of every window until dismissed
+    failures: Vec<String>,
+
+    drag: Option<Drag>,
+    // Last cursor position and the window it is relative to. The window a drag starts in
+    // keeps getting the cursor while the button is held, even outside of it
+    cursor: Option<(window::Id, Point)>,
+    // Where the windows are on the screen, to find the one a drag was released over.
+    // Platforms like Wayland never tell, so files can't be dragged between windows there
+    positions: BTreeMap<window::Id, Point>,
+    sizes: BTreeMap<window::Id, Size>,
+    // From the least to the most recently focused, the later ones are usually on top
+    focus_order: Vec<window::Id>,
+}
+
+// How far the cursor has to move with the button held before a file is dragged
+const DRAG_THRESHOLD: f32 = 8.0;
+
+// A file picked up in one window, dropped when the mouse is released over another
+struct Drag {
+    from: window::Id,
+    path: PathBuf,
+    // Where the button was pressed
+    origin: Point,
+    // Whether the cursor went far enough and left the window, a plain click never does
+    started: bool,
 }
 
 impl Filed {
     pub fn new() -> (Self, Task<Message>) {
-        (
-            Self {
-                view: View::Loading,
-            },
-            Task::perform(
-                async {
-                    // Simulate loading
-                    // std::thread::sleep(std::time::Duration::from_secs(2));
-                },
-                |_| Message::Loaded,
-            ),
-        )
+        let mut filed = Self {
+            windows: BTreeMap::new(),
+            clipboard: None,
+            queue: Queue::new(),
+            failures: vec![],
+            drag: None,
+            cursor: None,
+            positions: BTreeMap::new(),
+            sizes: BTreeMap::new(),
+            focus_order: vec![],
+        };
+
+        let settings = Settings::load();
+        let session = if settings.restore_session { Session::load() } else { Session::default() };
+        let windows = if session.windows.is_empty() {
+            vec![WindowState::at(settings.start_dir())]
+        } else {
+            session.windows
+        };
+        let task = Task::batch(windows.into_iter().map(|state| filed.open_window(state)).collect::<Vec<_>>());
+        (filed, task)
+    }
+
+    fn open_window(&mut self, state: WindowState) -> Task<Message> {
+        let mut settings = window::Settings::default();
+        if let Some((width, height)) = state.size {
+            settings.size = Size::new(width, height);
+        }
+        let (id, task) = window::open(settings);
+        self.windows.insert(id, View::Loading);
+        task.map(move |id| Message::WindowOpened(id, state.clone()))
+    }
+
+    // Only the windows still open when the last one closes are restored, like in browsers
+    fn save_session(&self) {
+        Session {
+            windows: self.windows.values().filter_map(|view| match view {
+                View::Tabs(tabs) => Some(tabs.session()),
+                View::Loading => None,
+            }).collect(),
+        }.save();
+    }
+
+    // The most recently focused window other than the one the drag started in under the cursor
+    fn drop_target(&self, drag: &Drag) -> Option<window::Id> {
+        let (relative_to, point) = self.cursor?;
+        let origin = self.positions.get(&relative_to)?;
+        let point = Point::new(origin.x + point.x, origin.y + point.y);
+        self.positions.iter()
+            .filter(|(id, _)| **id != drag.from)
+            .filter(|(id, position)| {
+                self.sizes.get(id).is_some_and(|size| Rectangle::new(**position, *size).contains(point))
+            })
+            .map(|(id, _)| *id)
+            .max_by_key(|id| self.focus_order.iter().position(|focused| focused == id))
+    }
+
+    fn tabs(&mut self, id: window::Id) -> Option<&mut tabs::Tabs> {
+        match self.windows.get_mut(&id) {
+            Some(View::Tabs(tabs)) => Some(tabs),
+            _ => None,
+        }
+    }
+
+    pub fn title(&self, id: window::Id) -> String {
+        let title = match self.windows.get(&id) {
+            Some(View::Tabs(tabs)) => tabs.title(),
+            _ => "Filed - Loading...".to_string(),
+        };
+        match self.queue.len() {
+            0 => title,
+            1 => format!("{} (1 operation running)", title),
+            n => format!("{} ({} operations running)", title, n),
+        }
     }
 
-    pub fn title(&self) -> String {
-        match &self.view {
-            View::Loading => "Filed - Loading...".to_string(),
-            View::Explorer(explorer) => explorer.title(),
+    fn enqueue(&mut self, job: Job) -> Task<Message> {
+        match self.queue.push(job) {
+            Some(job) => fileops::run(job).map(Message::OperationFinished),
+            None => Task::none(),
         }
     }
 
+    // Reloads `dir` in every window that shows it
+    fn reload(&mut self, dir: &PathBuf) -> Task<Message> {
+        Task::batch(self.windows.iter_mut().filter_map(|(&id, view)| match view {
+            View::Tabs(tabs) => Some(tabs.reload(dir).map(move |msg| Message::Tabs(id, msg))),
+            View::Loading => None,
+        }).collect::<Vec<_>>())
+    }
+
     fn update(&mut self, message: Message) -> Task<Message> {
         match message {
-            Message::Loaded => {
-                let (expl, task) = explorer::Explorer::open();
-                self.view = View::Explorer(expl);   
-                task.map(Message::Explorer)
+            Message::WindowOpened(id, state) => {
+                let (tabs, task) = tabs::Tabs::restore(state);
+                self.windows.insert(id, View::Tabs(tabs));
+                Task::batch(vec![
+                    task.map(move |msg| Message::Tabs(id, msg)),
+                    window::get_size(id).map(move |size| {
+                        Message::Event(id, Event::Window(window::Event::Resized(size)), event::Status::Ignored)
+                    }),
+                    // Unknown on platforms that don't tell windows where they are, like Wayland
+                    window::get_position(id).and_then(move |position| {
+                        Task::done(Message::Event(id, Event::Window(window::Event::Moved(position)), event::Status::Ignored))
+                    }),
+                ])
+            }
+            Message::WindowClosed(id) => {
+                if self.windows.len() == 1 {
+                    self.save_session();
+                }
+                self.windows.remove(&id);
+                self.positions.remove(&id);
+                self.sizes.remove(&id);
+                self.focus_order.retain(|focused| *focused != id);
+                if self.windows.is_empty() {
+                    return iced::exit();
+                }
+                Task::none()
+            }
+            Message::Event(id, event, status) => {
+                match &event {
+                    Event::Window(window::Event::Opened { position, size }) => {
+                        self.sizes.insert(id, *size);
+                        if let Some(position) = position {
+                            self.positions.insert(id, *position);
+                        }
+                    }
+                    Event::Window(window::Event::Moved(position)) => {
+                        self.positions.insert(id, *position);
+                    }
+                    Event::Window(window::Event::Resized(size)) => {
+                        self.sizes.insert(id, *size);
+                    }
+                    Event::Window(window::Event::Focused) => {
+                        self.focus_order.retain(|focused| *focused != id);
+                        self.focus_order.push(id);
+                    }
+                    Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                        self.cursor = Some((id, *position));
+                        if let Some(drag) = self.drag.as_mut().filter(|drag| drag.from == id && !drag.started) {
+                            let outside = self.sizes.get(&id).is_some_and(|size| !Rectangle::with_size(*size).contains(*position));
+                            drag.started = outside && position.distance(drag.origin) > DRAG_THRESHOLD;
+                        }
+                    }
+                    Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                        // Released before leaving the window, the file was only clicked
+                        if let Some(drag) = self.drag.take().filter(|drag| drag.started) {
+                            let dest_dir = self.drop_target(&drag).and_then(|to| self.tabs(to)).map(|tabs| tabs.current_path());
+                            if let Some(dest_dir) = dest_dir {
+                                return self.enqueue(Job { operation: Operation::Copy, src: drag.path, dest_dir });
+                            }
+                        }
+                    }
+                    Event::Window(window::Event::FileDropped(src)) => {
+                        // Files dragged in from other applications are copied
+                        if let Some(dest_dir) = self.tabs(id).map(|tabs| tabs.current_path()) {
+                            return self.enqueue(Job { operation: Operation::Copy, src: src.clone(), dest_dir });
+                        }
+                    }
+                    _ => {}
+                }
+                let Some(tabs) = self.tabs(id) else { return Task::none() };
+                let messages = tabs.handle_event(event, status);
+                Task::batch(messages.into_iter().map(|msg| self.update(Message::Tabs(id, msg))).collect::<Vec<_>>())
             }
-            Message::Explorer(msg) => {
-                if let View::Explorer(expl) = &mut self.view {
-                    expl.update(msg).map(Message::Explorer)
-                } else {
-                    Task::none()
+            Message::Tabs(id, msg) => {
+                // Files are selected when the button is pressed on them, which may start a drag
+                if let tabs::Message::Explorer(_, explorer::Message::SelectFile(data)) = &msg {
+                    self.drag = self.cursor.filter(|(window, _)| *window == id).map(|(_, origin)| Drag {
+                        from: id,
+                        path: data.path.clone(),
+                        origin,
+                        started: false,
+                    });
+                }
+                let Some(tabs) = self.tabs(id) else { return Task::none() };
+                match msg {
+                    tabs::Message::NewWindow => {
+                        let path = tabs.current_path();
+                        self.open_window(WindowState::at(path))
+                    }
+                    tabs::Message::Explorer(_, explorer::Message::OpenInNewWindow(path)) => self.open_window(WindowState::at(path)),
+                    tabs::Message::CloseWindow => window::close(id),
+                    tabs::Message::Copy | tabs::Message::Cut => {
+                        let operation = if matches!(msg, tabs::Message::Cut) { Operation::Move } else { Operation::Copy };
+                        if let Some(path) = tabs.selection() {
+                            self.clipboard = Some(Clipboard { operation, paths: vec![path] });
+                        }
+                        Task::none()
+                    }
+                    tabs::Message::Paste => {
+                        let dest_dir = tabs.current_path();
+                        let Some(clipboard) = self.clipboard.clone() else { return Task::none() };
+                        // Cut files can only be pasted once
+                        if clipboard.operation == Operation::Move {
+                            self.clipboard = None;
+                        }
+                        Task::batch(clipboard.paths.into_iter().map(|src| {
+                            self.enqueue(Job { operation: clipboard.operation, src, dest_dir: dest_dir.clone() })
+                        }).collect::<Vec<_>>())
+                    }
+                    tabs::Message::Transfer(operation) => {
+                        let Some((src, dest_dir)) = tabs.transfer() else { return Task::none() };
+                        self.enqueue(Job { operation, src, dest_dir })
+                    }
+                    msg => {
+                        let task = tabs.update(msg).map(move |msg| Message::Tabs(id, msg));
+
+                        // Bookmarks and other settings are shared with the other windows
+                        if let Some(settings) = tabs.take_changed_settings() {
+                            for (_, view) in self.windows.iter_mut().filter(|(other, _)| **other != id) {
+                                if let View::Tabs(tabs) = view {
+                                    tabs.set_settings(settings.clone());
+                                }
+                            }
+                        }
+                        task
+                    }
                 }
             }
+            Message::OperationFinished(result) => {
+                let (finished, next) = self.queue.finish();
+                let mut tasks = vec![];
+                if let Some(job) = finished {
+                    if let Err(e) = result {
+                        let verb = if job.operation == Operation::Move { "Moving" } else { "Copying" };
+                        let name = job.src.file_name().unwrap_or(job.src.as_os_str()).to_string_lossy();
+                        self.failures.push(format!("{} {} failed: {}", verb, name, e));
+                    }
+                    tasks.push(self.reload(&job.dest_dir));
+                    if job.operation == Operation::Move {
+                        if let Some(parent) = job.src.parent() {
+                            tasks.push(self.reload(&parent.to_path_buf()));
+                        }
+                    }
+                }
+                if let Some(job) = next {
+                    tasks.push(fileops::run(job).map(Message::OperationFinished));
+                }
+                Task::batch(tasks)
+            }
+            Message::DismissFailure(i) => {
+                if i < self.failures.len() {
+                    self.failures.remove(i);
+                }
+                Task::none()
+            }
         }
     }
 
-    fn view(&self) -> Element<Message> {
-        match &self.view {
-            View::Loading => {
+    fn view(&self, id: window::Id) -> Element<Message> {
+        match self.windows.get(&id) {
+            Some(View::Tabs(tabs)) => {
+                let failures = self.failures.iter().enumerate().map(|(i, failure)| {
+                    container(
+                        row![
+                            text(failure).size(14).width(Length::Fill).style(|theme: &Theme| text::Style {
+                                color: Some(theme.palette().danger),
+                            }),
+                            button(text("×").size(14))
+                                .on_press(Message::DismissFailure(i))
+                                .padding(0)
+                                .style(button::text),
+                        ]
+                            .spacing(5)
+                    )
+                        .padding(Padding::new(4.0).left(10).right(10))
+                        .into()
+                });
+                column![tabs.view().map(move |msg| Message::Tabs(id, msg))]
+                    .extend(failures)
+                    .into()
+            }
+            _ => {
                 row![
                     text("Loading..."),
                 ]
                 .into()
             }
-            View::Explorer(explorer) => {
-                explorer.view().map(Message::Explorer)
-            }
         }
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        match &self.view {
-            View::Loading => Subscription::none(),
-            View::Explorer(explorer) => explorer.subscription().map(Message::Explorer),
-        }
+        Subscription::batch(vec![
+            event::listen_with(|event, status, id| Some(Message::Event(id, event, status))),
+            window::close_events().map(Message::WindowClosed),
+        ])
     }
 
-    fn theme(&self) -> Theme {
+    fn theme(&self, _id: window::Id) -> Theme {
         Theme::Dracula
     }
-}
\ No newline at end of file
+}
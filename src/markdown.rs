@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use iced::widget::image::Handle;
+
+use crate::highlight::{self, Token};
+
+// Images in a document are scaled down to this width
+const IMAGE_WIDTH: u32 = 600;
+
+// Bigger images aren't decoded, they are shown by their alt text
+const MAX_IMAGE_SIZE: u64 = 32 * 1024 * 1024;
+
+#[derive(Debug, Clone, Default)]
+pub struct Span {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+    pub link: Option<String>,
+    // Local image, resolved relative to the document
+    pub image: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Block {
+    Heading(u8, Vec<Span>),
+    Paragraph(Vec<Span>),
+    Quote(Vec<Span>),
+    // Nesting depth, the marker ("•" or "1.") and the content
+    ListItem(usize, String, Vec<Span>),
+    // Language name if it's one we can highlight
+    Code(Option<&'static str>, Vec<Vec<(String, Token)>>),
+    Table(Vec<Vec<Span>>, Vec<Vec<Vec<Span>>>),
+    Rule,
+}
+
+// A small CommonMark subset, enough for READMEs and notes
+pub fn parse(source: &str, dir: &Path) -> Vec<Block> {
+    let mut blocks = vec![];
+    let mut paragraph: Vec<&str> = vec![];
+    let mut lines = source.lines().peekable();
+
+    let flush = |paragraph: &mut Vec<&str>, blocks: &mut Vec<Block>| {
+        if !paragraph.is_empty() {
+            blocks.push(Block::Paragraph(inline(&paragraph.join(" "), dir)));
+            paragraph.clear();
+        }
+    };
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() {
+            flush(&mut paragraph, &mut blocks);
+            continue;
+        }
+
+        if let Some(fence) = ["```", "~~~"].into_iter().find(|f| trimmed.starts_with(f)) {
+            flush(&mut paragraph, &mut blocks);
+            let info = trimmed.trim_start_matches(fence).trim();
+            let mut code = vec![];
+            for line in lines.by_ref() {
+                if line.trim().starts_with(fence) {
+                    break;
+                }
+                code.push(line);
+            }
+            let language = highlight::by_name(info);
+            let lines = match language {
+                Some(language) => highlight::highlight(code.into_iter(), language),
+                None => code.into_iter().map(|line| vec![(line.to_owned(), Token::Plain)]).collect(),
+            };
+            blocks.push(Block::Code(language.map(|l| l.name), lines));
+            continue;
+        }
+
+        // Setext headings underline the paragraph before them
+        if !paragraph.is_empty() && (is_underline(trimmed, '=') || is_underline(trimmed, '-')) {
+            let level = if trimmed.starts_with('=') { 1 } else { 2 };
+            blocks.push(Block::Heading(level, inline(&paragraph.join(" "), dir)));
+            paragraph.clear();
+            continue;
+        }
+
+        if let Some(level) = heading_level(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            let text = trimmed[level as usize..].trim().trim_end_matches('#').trim();
+            blocks.push(Block::Heading(level, inline(text, dir)));
+            continue;
+        }
+
+        if is_rule(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Rule);
+            continue;
+        }
+
+        if let Some(quote) = trimmed.strip_prefix('>') {
+            flush(&mut paragraph, &mut blocks);
+            let mut text = quote.trim().to_owned();
+            while let Some(next) = lines.peek().copied().and_then(|l| l.trim().strip_prefix('>')) {
+                text.push(' ');
+                text.push_str(next.trim());
+                lines.next();
+            }
+            blocks.push(Block::Quote(inline(&text, dir)));
+            continue;
+        }
+
+        if let Some((marker, text)) = list_item(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            let indent = line.len() - line.trim_start().len();
+            blocks.push(Block::ListItem(indent / 2, marker, inline(text, dir)));
+            continue;
+        }
+
+        if trimmed.contains('|') && lines.peek().is_some_and(|next| is_table_separator(next)) {
+            flush(&mut paragraph, &mut blocks);
+            lines.next();
+            let header = cells(trimmed).into_iter().map(|cell| inline(cell, dir)).collect();
+            let mut rows = vec![];
+            while let Some(row) = lines.peek().copied().filter(|l| l.contains('|')) {
+                rows.push(cells(row).into_iter().map(|cell| inline(cell, dir)).collect());
+                lines.next();
+            }
+            blocks.push(Block::Table(header, rows));
+            continue;
+        }
+
+        // Raw HTML isn't rendered
+        if trimmed.starts_with('<') && trimmed.ends_with('>') {
+            flush(&mut paragraph, &mut blocks);
+            continue;
+        }
+
+        paragraph.push(trimmed);
+    }
+    flush(&mut paragraph, &mut blocks);
+    blocks
+}
+
+// Decodes the local images the document shows, stops early once cancelled
+pub fn load_images(blocks: &[Block], cancelled: &AtomicBool) -> HashMap<PathBuf, Handle> {
+    let spans = blocks.iter().flat_map(|block| match block {
+        Block::Heading(_, spans) | Block::Paragraph(spans) | Block::Quote(spans) | Block::ListItem(_, _, spans) => spans.iter().collect(),
+        Block::Table(header, rows) => header.iter().chain(rows.iter().flatten()).flatten().collect(),
+        Block::Code(..) | Block::Rule => vec![],
+    });
+
+    let mut images = HashMap::new();
+    for path in spans.filter_map(|span| span.image.clone()) {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+        if images.contains_key(&path) {
+            continue;
+        }
+        // Opening a FIFO or a device would block or never end
+        if !std::fs::metadata(&path).is_ok_and(|m| m.is_file() && m.len() <= MAX_IMAGE_SIZE) {
+            continue;
+        }
+        if let Ok(image) = image::open(&path) {
+            let image = image.resize(IMAGE_WIDTH, IMAGE_WIDTH * 4, image::imageops::FilterType::Triangle).to_rgba8();
+            let (width, height) = image.dimensions();
+            images.insert(path, Handle::from_rgba(width, height, image.into_raw()));
+        }
+    }
+    images
+}
+
+fn heading_level(line: &str) -> Option<u8> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = &line[level..];
+    ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' '))).then_some(level as u8)
+}
+
+fn is_underline(line: &str, c: char) -> bool {
+    line.chars().all(|ch| ch == c)
+}
+
+fn is_rule(line: &str) -> bool {
+    let chars = line.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    chars.len() >= 3 && ['-', '*', '_'].iter().any(|&c| chars.chars().all(|ch| ch == c))
+}
+
+fn list_item(line: &str) -> Option<(String, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(text) = line.strip_prefix(bullet) {
+            // Task lists show their box
+            let text = text.trim_start();
+            return Some(match text.get(..3) {
+                Some("[ ]") => ("☐".to_owned(), text[3..].trim_start()),
+                Some("[x]") | Some("[X]") => ("☑".to_owned(), text[3..].trim_start()),
+                _ => ("•".to_owned(), text),
+            });
+        }
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    let rest = &line[digits..];
+    if digits > 0 && (rest.starts_with(". ") || rest.starts_with(") ")) {
+        return Some((line[..digits + 1].to_owned(), rest[2..].trim_start()));
+    }
+    None
+}
+
+fn is_table_separator(line: &str) -> bool {
+    let line = line.trim();
+    line.contains('-') && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+fn cells(line: &str) -> Vec<&str> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    line.split('|').map(str::trim).collect()
+}
+
+// Emphasis, code, links and images inside a block
+fn inline(text: &str, dir: &Path) -> Vec<Span> {
+    let mut spans = vec![];
+    let mut current = Span::default();
+    let mut chars = text.char_indices().peekable();
+
+    let push = |current: &mut Span, spans: &mut Vec<Span>| {
+        if !current.text.is_empty() {
+            let next = Span { text: String::new(), ..current.clone() };
+            spans.push(std::mem::replace(current, next));
+        }
+    };
+
+    while let Some((i, c)) = chars.next() {
+        let rest = &text[i..];
+        match c {
+            '\\' => {
+                if let Some((_, next)) = chars.next() {
+                    current.text.push(next);
+                }
+            }
+            '`' => {
+                let Some(end) = rest[1..].find('`') else {
+                    current.text.push(c);
+                    continue;
+                };
+                push(&mut current, &mut spans);
+                spans.push(Span { text: rest[1..end + 1].to_owned(), code: true, ..Span::default() });
+                skip(&mut chars, i + end + 2);
+            }
+            '*' | '_' => {
+                let double = rest[1..].starts_with(c);
+                // Underscores inside words like snake_case are not emphasis
+                let intraword = c == '_' && text[..i].chars().last().is_some_and(char::is_alphanumeric)
+                    && rest[1..].chars().next().is_some_and(char::is_alphanumeric);
+                if intraword {
+                    current.text.push(c);
+                    continue;
+                }
+                push(&mut current, &mut spans);
+                if double {
+                    current.bold = !current.bold;
+                    chars.next();
+                } else {
+                    current.italic = !current.italic;
+                }
+            }
+            '!' if rest.starts_with("![") => {
+                let Some((alt, target, len)) = link(&rest[1..]) else {
+                    current.text.push(c);
+                    continue;
+                };
+                push(&mut current, &mut spans);
+                let is_local = !target.contains("://");
+                spans.push(Span {
+                    text: alt.to_owned(),
+                    image: is_local.then(|| dir.join(target)),
+                    ..Span::default()
+                });
+                skip(&mut chars, i + 1 + len);
+            }
+            '[' => {
+                let Some((label, target, len)) = link(rest) else {
+                    current.text.push(c);
+                    continue;
+                };
+                push(&mut current, &mut spans);
+                // Anchors inside the document aren't followed
+                let url = if target.starts_with('#') {
+                    None
+                } else if target.contains("://") || target.starts_with("mailto:") {
+                    Some(target.to_owned())
+                } else {
+                    Some(dir.join(target).to_string_lossy().to_string())
+                };
+                spans.push(Span { text: label.to_owned(), link: url, ..current.clone() });
+                skip(&mut chars, i + len);
+            }
+            '<' if rest.starts_with("<http") && rest.contains('>') => {
+                let end = rest.find('>').unwrap_or(rest.len());
+                push(&mut current, &mut spans);
+                spans.push(Span { text: rest[1..end].to_owned(), link: Some(rest[1..end].to_owned()), ..Span::default() });
+                skip(&mut chars, i + end + 1);
+            }
+            _ => current.text.push(c),
+        }
+    }
+    push(&mut current, &mut spans);
+    spans
+}
+
+// "[label](target)" at the start of `text`, with the length it takes up.
+// The label ends at the first "]", so "[a] x [b](c)" isn't a link
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let close = text.find(']')?;
+    if !text[close..].starts_with("](") {
+        return None;
+    }
+    let end = text[close..].find(')')? + close;
+    let target = text[close + 2..end].split_whitespace().next().unwrap_or("");
+    Some((&text[1..close], target, end + 1))
+}
+
+fn skip(chars: &mut std::iter::Peekable<std::str::CharIndices>, until: usize) {
+    while chars.peek().is_some_and(|(i, _)| *i < until) {
+        chars.next();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn link_label_ends_at_the_first_bracket() {
+        assert_eq!(link("[a](b) rest"), Some(("a", "b", 6)));
+        assert_eq!(link("[a](b \"title\")"), Some(("a", "b", 14)));
+        assert_eq!(link("[a] x [b](c)"), None);
+        assert_eq!(link("[a"), None);
+        assert_eq!(link("[a](b"), None);
+    }
+
+    #[test]
+    fn bracketed_text_stays_text() {
+        let spans = inline("[a] x [b](c)", Path::new("/"));
+        let text = spans.iter().map(|span| span.text.as_str()).collect::<String>();
+        assert_eq!(text, "[a] x b");
+        assert_eq!(spans.iter().filter(|span| span.link.is_some()).count(), 1);
+    }
+}
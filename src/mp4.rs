@@ -0,0 +1,142 @@
+// The box ("atom") structure shared by MP4, M4A and QuickTime files
+
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::utils::read_at;
+
+// Boxes bigger than this aren't read, "moov" stays a few MB even for long movies
+const MAX_BOX_SIZE: u64 = 64 * 1024 * 1024;
+
+// The body of the first top level box of this type, the boxes before it are skipped unread
+pub fn read_box<R: Read + Seek>(reader: &mut R, kind: &[u8; 4]) -> Option<Vec<u8>> {
+    let len = reader.seek(SeekFrom::End(0)).ok()?;
+    let mut offset = 0u64;
+    while offset.saturating_add(8) <= len {
+        let header = read_at(reader, offset, 16)?;
+        let (header_len, size) = match u32_at(&header, 0)? {
+            1 => (16, u64_at(&header, 8)?),
+            0 => (8, len - offset),
+            size => (8, size as u64),
+        };
+        if size < header_len {
+            return None;
+        }
+        if header.get(4..8)? == kind {
+            let body = size - header_len;
+            return (body <= MAX_BOX_SIZE).then(|| read_at(reader, offset + header_len, body)).flatten();
+        }
+        offset = offset.checked_add(size)?;
+    }
+    None
+}
+
+// Type and body of every box in `data`, stops at the first one that doesn't fit
+pub fn atoms(data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        if rest.len() < 8 {
+            return None;
+        }
+        let size = u32::from_be_bytes(rest[0..4].try_into().ok()?) as u64;
+        let kind: [u8; 4] = rest[4..8].try_into().ok()?;
+        let (header, size) = match size {
+            // The size is a 64 bit number after the type
+            1 => (16, u64::from_be_bytes(rest.get(8..16)?.try_into().ok()?)),
+            // The box runs to the end of the file
+            0 => (8, rest.len() as u64),
+            size => (8, size),
+        };
+        if size < header || size > rest.len() as u64 {
+            return None;
+        }
+        let body = &rest[header as usize..size as usize];
+        rest = &rest[size as usize..];
+        Some((kind, body))
+    })
+}
+
+// The first box with the given type
+pub fn child<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    atoms(data).find(|(k, _)| k == kind).map(|(_, body)| body)
+}
+
+// Follows a path of box types like `[b"moov", b"udta"]`
+pub fn find<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    path.iter().try_fold(data, |data, kind| child(data, kind))
+}
+
+// Version, flags and then the body of a "full box"
+pub fn full_box(body: &[u8]) -> Option<(u8, &[u8])> {
+    Some((*body.first()?, body.get(4..)?))
+}
+
+pub fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+pub fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+pub fn u64_at(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(offset..offset + 8)?.try_into().ok()?))
+}
+
+// Timescale and duration from a "mvhd" or "mdhd" box.
+// A duration of all ones means it's unknown, it's returned as 0 then
+pub fn timing(body: &[u8]) -> Option<(u32, u64)> {
+    let (version, body) = full_box(body)?;
+    let (timescale, duration, unknown) = if version == 1 {
+        (u32_at(body, 16)?, u64_at(body, 20)?, u64::MAX)
+    } else {
+        (u32_at(body, 8)?, u32_at(body, 12)? as u64, u32::MAX as u64)
+    };
+    Some((timescale, if duration == unknown { 0 } else { duration }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn boxed(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        [&(body.len() as u32 + 8).to_be_bytes()[..], kind, body].concat()
+    }
+
+    #[test]
+    fn reads_a_box_after_skipping_others() {
+        let file = [boxed(b"ftyp", b"isom"), boxed(b"mdat", &[0; 100]), boxed(b"moov", b"body")].concat();
+        assert_eq!(read_box(&mut Cursor::new(&file), b"moov"), Some(b"body".to_vec()));
+        assert_eq!(read_box(&mut Cursor::new(&file), b"udta"), None);
+    }
+
+    #[test]
+    fn broken_boxes_are_not_read() {
+        // Smaller than its own header
+        assert_eq!(read_box(&mut Cursor::new(b"\0\0\0\x04moov"), b"moov"), None);
+        // Cut off after the header, what is there is read
+        assert_eq!(read_box(&mut Cursor::new(b"\0\0\0\x10moovab"), b"moov"), Some(b"ab".to_vec()));
+        assert_eq!(read_box(&mut Cursor::new(b"\0\0"), b"moov"), None);
+        // A 64 bit size which would overflow the offset
+        let huge = [&1u32.to_be_bytes()[..], b"mdat", &u64::MAX.to_be_bytes()].concat();
+        assert_eq!(read_box(&mut Cursor::new([huge, boxed(b"moov", b"")].concat()), b"moov"), None);
+    }
+
+    #[test]
+    fn atoms_stop_at_the_first_box_that_does_not_fit() {
+        let data = [boxed(b"free", b"ab"), b"\0\0\0\x20cut".to_vec()].concat();
+        assert_eq!(atoms(&data).map(|(kind, _)| kind).collect::<Vec<_>>(), vec![*b"free"]);
+        assert_eq!(find(&boxed(b"moov", &boxed(b"udta", b"x")), &[b"moov", b"udta"]), Some(&b"x"[..]));
+        assert_eq!(find(b"\0\0\0", &[b"moov"]), None);
+    }
+
+    #[test]
+    fn unknown_durations_are_zero() {
+        let v0 = [&[0; 4][..], &[0; 8], &1000u32.to_be_bytes(), &u32::MAX.to_be_bytes()].concat();
+        assert_eq!(timing(&v0), Some((1000, 0)));
+        let v1 = [&[1, 0, 0, 0][..], &[0; 16], &1u32.to_be_bytes(), &u64::MAX.to_be_bytes()].concat();
+        assert_eq!(timing(&v1), Some((1, 0)));
+        assert_eq!(timing(&v1[..20]), None);
+    }
+}
@@ -0,0 +1,147 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::time::SystemTime;
+
+use chrono::{Local, NaiveDate, TimeZone};
+use exif::{Field, In, Tag, Value};
+use image::DynamicImage;
+
+// What the preview shows of a photo's EXIF data
+#[derive(Debug, Clone, Default)]
+pub struct Exif {
+    pub camera: Option<String>,
+    pub lens: Option<String>,
+    pub exposure: Option<String>,
+    pub aperture: Option<String>,
+    pub focal_length: Option<String>,
+    pub iso: Option<String>,
+    pub taken: Option<SystemTime>,
+    // Latitude and longitude in degrees, south and west are negative
+    pub gps: Option<(f64, f64)>,
+    // 1 to 8 as in the EXIF spec, 1 is upright
+    pub orientation: u32,
+}
+
+impl Exif {
+    // Label and value of every known field, in the order they're shown
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let orientation = match self.orientation {
+            2 => Some("Mirrored"),
+            3 => Some("Rotated 180°"),
+            4 => Some("Mirrored, rotated 180°"),
+            5 => Some("Mirrored, rotated 90° CW"),
+            6 => Some("Rotated 90° CW"),
+            7 => Some("Mirrored, rotated 90° CCW"),
+            8 => Some("Rotated 90° CCW"),
+            _ => None,
+        };
+        [
+            ("Camera", self.camera.clone()),
+            ("Lens", self.lens.clone()),
+            ("Exposure", self.exposure.clone()),
+            ("Aperture", self.aperture.clone()),
+            ("Focal Length", self.focal_length.clone()),
+            ("ISO", self.iso.clone()),
+            ("Taken", self.taken.map(|t| chrono::DateTime::<Local>::from(t).format("%Y-%m-%d %H:%M:%S").to_string())),
+            ("Location", self.gps.map(|(lat, lon)| format!("{:.5}, {:.5}", lat, lon))),
+            ("Orientation", orientation.map(str::to_owned)),
+        ]
+            .into_iter()
+            .filter_map(|(label, value)| value.map(|v| (label, v)))
+            .collect()
+    }
+}
+
+// None if the file has no EXIF data or it can't be read
+pub fn read(path: &Path) -> Option<Exif> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new().read_from_container(&mut BufReader::new(file)).ok()?;
+    let field = |tag| exif.get_field(tag, In::PRIMARY);
+    let ascii = |tag| field(tag).and_then(ascii_value);
+
+    let camera = match (ascii(Tag::Make), ascii(Tag::Model)) {
+        // Most models already start with the make, "Canon Canon EOS 5D" reads badly
+        (Some(make), Some(model)) if model.to_lowercase().starts_with(&make.to_lowercase()) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => make.or(model),
+    };
+
+    Some(Exif {
+        camera,
+        lens: ascii(Tag::LensModel),
+        exposure: field(Tag::ExposureTime).and_then(rational).map(|t| {
+            if t > 0.0 && t < 1.0 { format!("1/{:.0} s", 1.0 / t) } else { format!("{} s", t) }
+        }),
+        aperture: field(Tag::FNumber).and_then(rational).map(|f| format!("f/{:.1}", f)),
+        focal_length: field(Tag::FocalLength).and_then(rational).map(|f| format!("{:.0} mm", f)),
+        iso: field(Tag::PhotographicSensitivity).and_then(|f| f.value.get_uint(0)).map(|iso| iso.to_string()),
+        taken: field(Tag::DateTimeOriginal).or_else(|| field(Tag::DateTime)).and_then(date_time),
+        gps: gps(&exif),
+        orientation: field(Tag::Orientation).and_then(|f| f.value.get_uint(0)).unwrap_or(1),
+    })
+}
+
+// Only the capture date, for the list column
+pub fn taken(path: &Path) -> Option<SystemTime> {
+    read(path)?.taken
+}
+
+// Decodes an image and turns it the way the camera was held
+pub fn open(path: &Path) -> Option<DynamicImage> {
+    let image = image::open(path).ok()?;
+    let orientation = read(path).map(|exif| exif.orientation).unwrap_or(1);
+    Some(orient(image, orientation))
+}
+
+pub fn orient(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+fn ascii_value(field: &Field) -> Option<String> {
+    let Value::Ascii(values) = &field.value else { return None };
+    let value = String::from_utf8_lossy(values.first()?).trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_owned();
+    (!value.is_empty()).then_some(value)
+}
+
+fn rational(field: &Field) -> Option<f64> {
+    match &field.value {
+        Value::Rational(values) => values.first().map(|r| r.to_f64()),
+        Value::SRational(values) => values.first().map(|r| r.to_f64()),
+        _ => None,
+    }
+}
+
+// EXIF dates carry no time zone, they're taken as local time
+fn date_time(field: &Field) -> Option<SystemTime> {
+    let Value::Ascii(values) = &field.value else { return None };
+    let dt = exif::DateTime::from_ascii(values.first()?).ok()?;
+    let naive = NaiveDate::from_ymd_opt(dt.year as i32, dt.month as u32, dt.day as u32)?
+        .and_hms_opt(dt.hour as u32, dt.minute as u32, dt.second as u32)?;
+    Local.from_local_datetime(&naive).earliest().map(SystemTime::from)
+}
+
+fn gps(exif: &exif::Exif) -> Option<(f64, f64)> {
+    let coordinate = |tag, reference, negative: &str| {
+        let Value::Rational(parts) = &exif.get_field(tag, In::PRIMARY)?.value else { return None };
+        let degrees = parts.iter()
+            .zip([1.0, 60.0, 3600.0])
+            .map(|(part, divisor)| part.to_f64() / divisor)
+            .sum::<f64>();
+        let flip = exif.get_field(reference, In::PRIMARY).and_then(ascii_value).is_some_and(|r| r == negative);
+        Some(if flip { -degrees } else { degrees })
+    };
+    Some((
+        coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S")?,
+        coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, "W")?,
+    ))
+}
@@ -1,4 +1,7 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::process::Command;
+use std::sync::OnceLock;
 
 pub struct Platform;
 
@@ -8,9 +11,7 @@ impl Platform {
     pub fn home_dir() -> PathBuf {
         #[cfg(target_os = "windows")]
         let default = PathBuf::from("C:\\");
-        #[cfg(target_os = "linux")] 
-        let default = PathBuf::from("/");
-        #[cfg(target_os = "macos")]
+        #[cfg(not(target_os = "windows"))]
         let default = PathBuf::from("/");
 
         dirs::home_dir().unwrap_or(default)
@@ -28,4 +29,56 @@ impl Platform {
 
         dirs.iter().filter(|d| d.is_some()).map(|d| d.clone().unwrap()).collect::<Vec<_>>()
     }
-}
\ No newline at end of file
+
+    // Opens a file or URL with the default application of the desktop
+    pub fn open(target: &str) -> std::io::Result<()> {
+        #[cfg(target_os = "windows")]
+        let mut command = {
+            let mut command = Command::new("cmd");
+            command.args(["/C", "start", ""]);
+            command
+        };
+        #[cfg(target_os = "macos")]
+        let mut command = Command::new("open");
+        // Linux and the BSDs
+        #[cfg(all(unix, not(target_os = "macos")))]
+        let mut command = Command::new("xdg-open");
+        #[cfg(not(any(target_os = "windows", unix)))]
+        return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "opening files is not supported on this platform"));
+
+        #[cfg(any(target_os = "windows", unix))]
+        return command.arg(target).spawn().map(|_| ());
+    }
+
+    // Resolves a uid to a user name, falling back to the number itself
+    pub fn user_name(uid: u32) -> String {
+        static USERS: OnceLock<HashMap<u32, String>> = OnceLock::new();
+        USERS.get_or_init(|| id_names("/etc/passwd"))
+            .get(&uid)
+            .cloned()
+            .unwrap_or(uid.to_string())
+    }
+
+    // Resolves a gid to a group name, falling back to the number itself
+    pub fn group_name(gid: u32) -> String {
+        static GROUPS: OnceLock<HashMap<u32, String>> = OnceLock::new();
+        GROUPS.get_or_init(|| id_names("/etc/group"))
+            .get(&gid)
+            .cloned()
+            .unwrap_or(gid.to_string())
+    }
+}
+
+// Reads `name:password:id:...` lines as found in /etc/passwd and /etc/group
+fn id_names(file: &str) -> HashMap<u32, String> {
+    std::fs::read_to_string(file)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((id, name.to_owned()))
+        })
+        .collect()
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use iced::futures::channel::oneshot;
+use iced::widget::image::Handle;
+use iced::Task;
+use memmap2::Mmap;
+
+use crate::audio::{self, Tags};
+use crate::components::fileitem::{FileData, FileType};
+use crate::document::{self, DocumentInfo};
+use crate::hex;
+use crate::highlight::{self, Token};
+use crate::markdown::{self, Block};
+use crate::photo::{self, Exif};
+use crate::structured::{self, Format, Node, ParseError};
+use crate::tabular::{self, Table};
+use crate::typeface::{self, FontInfo};
+use crate::video::{self, VideoInfo};
+
+// How much of a text file is read for the preview, per page
+pub const TEXT_LIMIT: usize = 64 * 1024;
+
+// JSON, YAML and TOML have to be read whole to be parsed, up to this size
+pub const STRUCTURED_LIMIT: usize = 16 * 1024 * 1024;
+
+// Highlighted lines shown per page, every line is its own row of widgets
+pub const LINE_LIMIT: usize = 500;
+
+// Images are scaled down to fit in a square of this size
+const IMAGE_SIZE: u32 = 1024;
+
+// Text is read in small blocks so a cancelled preview stops quickly
+const BLOCK_SIZE: usize = 8 * 1024;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone)]
+pub enum Content {
+    // Already turned upright, `exif` is None for images without it
+    Image { handle: Handle, exif: Option<Exif> },
+    Text { text: String, truncated: bool },
+    Code { language: &'static str, lines: Vec<Vec<(String, Token)>>, truncated: bool },
+    // Rendered by the preview, `source` is shown when switched to raw
+    Markdown { source: String, blocks: Vec<Block>, images: HashMap<PathBuf, Handle>, truncated: bool },
+    Table { table: Table, truncated: bool },
+    // `source` is kept to show where parsing failed, `root` is None if the file is too big to parse
+    Structured { format: Format, root: Option<Result<Node, ParseError>>, source: String },
+    // `cover` is decoded from the tags, which keep no copy of it
+    Audio { tags: Tags, cover: Option<Handle> },
+    Video(VideoInfo),
+    Document(DocumentInfo),
+    // The font's data is registered with the renderer before the sample is shown,
+    // `modified` tells an edited file from the version registered before
+    Font { info: FontInfo, data: Arc<Vec<u8>>, renderable: bool, modified: Option<SystemTime> },
+    // Binary files, shown a page at a time
+    Hex(Arc<Mmap>),
+    // Nothing to render, only the metadata is shown
+    Empty,
+}
+
+#[derive(Debug, Clone)]
+pub struct Preview {
+    // With its metadata loaded
+    pub data: FileData,
+    pub content: Content,
+    // How many pages of text were loaded, "load more" asks for one more
+    pub pages: usize,
+}
+
+// Keeps track of the preview being loaded so a stale one can be dropped, like `Loader`
+#[derive(Debug, Default)]
+pub struct PreviewLoader {
+    id: u64,
+    cancelled: Option<Arc<AtomicBool>>,
+}
+
+impl PreviewLoader {
+    // Cancels the previous preview (if any) and starts loading `pages` pages of `data`
+    pub fn start(&mut self, data: FileData, pages: usize) -> Task<(u64, Option<Preview>)> {
+        self.cancel();
+        self.id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.cancelled = Some(cancelled.clone());
+
+        let id = self.id;
+        let (sender, receiver) = oneshot::channel();
+        rayon::spawn(move || {
+            let _ = sender.send(load(data, pages, &cancelled));
+        });
+        Task::perform(receiver, move |result| (id, result.ok().flatten()))
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some(cancelled) = self.cancelled.take() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_current(&self, id: u64) -> bool {
+        self.cancelled.is_some() && id == self.id
+    }
+
+    pub fn finish(&mut self) {
+        self.cancelled = None;
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.cancelled.is_some()
+    }
+}
+
+// Returns None once cancelled
+fn load(data: FileData, pages: usize, cancelled: &AtomicBool) -> Option<Preview> {
+    let data = data.with_metadata().with_item_count().with_taken();
+    if cancelled.load(Ordering::Relaxed) {
+        return None;
+    }
+
+    let content = if data.is_dir {
+        Content::Empty
+    } else if data.file_type == Some(FileType::Image) {
+        image(&data.path).unwrap_or(Content::Empty)
+    } else if let Some(info) = (data.file_type == Some(FileType::Document)).then(|| document::read(&data.path)).flatten() {
+        Content::Document(info)
+    } else if let Some(info) = (data.file_type == Some(FileType::Video)).then(|| video::read(&data.path)).flatten() {
+        Content::Video(info)
+    } else if let Some(content) = (data.file_type == Some(FileType::Audio)).then(|| audio(&data.path)).flatten() {
+        content
+    } else if let Some((info, font, renderable)) = typeface::is_font(&data.extension()).then(|| typeface::load(&data.path)).flatten() {
+        let modified = std::fs::metadata(&data.path).and_then(|m| m.modified()).ok();
+        Content::Font { info, data: Arc::new(font), renderable, modified }
+    } else if let Some(content) = text(&data.path, pages, cancelled) {
+        content
+    } else {
+        hex::map(&data.path).map(Content::Hex).unwrap_or(Content::Empty)
+    };
+
+    if cancelled.load(Ordering::Relaxed) {
+        return None;
+    }
+    Some(Preview { data, content, pages })
+}
+
+fn image(path: &Path) -> Option<Content> {
+    let exif = photo::read(path);
+    let image = image::open(path).ok()?.thumbnail(IMAGE_SIZE, IMAGE_SIZE);
+    let image = photo::orient(image, exif.as_ref().map(|e| e.orientation).unwrap_or(1)).to_rgba8();
+    let (width, height) = image.dimensions();
+    Some(Content::Image { handle: Handle::from_rgba(width, height, image.into_raw()), exif })
+}
+
+fn audio(path: &Path) -> Option<Content> {
+    let mut tags = audio::read(path, true)?;
+    let cover = tags.cover.take()
+        .and_then(|bytes| image::load_from_memory(&bytes).ok())
+        .map(|image| image.thumbnail(IMAGE_SIZE, IMAGE_SIZE).to_rgba8())
+        .map(|image| {
+            let (width, height) = image.dimensions();
+            Handle::from_rgba(width, height, image.into_raw())
+        });
+    Some(Content::Audio { tags, cover })
+}
+
+// Reads the start of the file, it only counts as text if it has no NUL bytes and is valid UTF-8.
+// Returns None for binary files and when cancelled
+fn text(path: &Path, pages: usize, cancelled: &AtomicBool) -> Option<Content> {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let format = Format::from_extension(&extension);
+    let limit = if format.is_some() { STRUCTURED_LIMIT } else { TEXT_LIMIT * pages };
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut bytes = Vec::with_capacity(TEXT_LIMIT);
+    let mut block = [0; BLOCK_SIZE];
+    let mut truncated = false;
+
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        match file.read(&mut block) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => bytes.extend_from_slice(&block[..n]),
+        }
+        if bytes.len() > limit {
+            bytes.truncate(limit);
+            truncated = true;
+            break;
+        }
+    }
+
+    if bytes.contains(&0) {
+        return None;
+    }
+    let text = match std::str::from_utf8(&bytes) {
+        Ok(text) => text.to_owned(),
+        // The limit may have cut a character in half
+        Err(e) if truncated && e.error_len().is_none() => String::from_utf8_lossy(&bytes[..e.valid_up_to()]).to_string(),
+        Err(_) => return None,
+    };
+
+    if extension == "md" || extension == "markdown" {
+        let blocks = markdown::parse(&text, path.parent().unwrap_or(Path::new("/")));
+        if cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        let images = markdown::load_images(&blocks, cancelled);
+        if cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        return Some(Content::Markdown { source: text, blocks, images, truncated });
+    }
+    if extension == "csv" || extension == "tsv" {
+        let (table, more_rows) = tabular::parse(&text, extension == "tsv", LINE_LIMIT * pages);
+        return Some(Content::Table { table, truncated: truncated || more_rows });
+    }
+    if let Some(format) = format {
+        // A cut off file would only fail to parse where it was cut
+        let root = (!truncated).then(|| structured::parse(&text, format));
+        return Some(Content::Structured { format, root, source: text });
+    }
+
+    let Some(language) = highlight::detect(path, &text) else {
+        return Some(Content::Text { text, truncated });
+    };
+    let line_limit = LINE_LIMIT * pages;
+    Some(Content::Code {
+        language: language.name,
+        lines: highlight::highlight(text.lines().take(line_limit), language),
+        truncated: truncated || text.lines().nth(line_limit).is_some(),
+    })
+}
@@ -0,0 +1,23 @@
+use crate::components::fileitem::FileData;
+use crate::sorting::collation_key;
+
+// Every word of the query has to match, ignoring case and accents.
+// "key:value" looks at one field like "author:knuth" or "pages:12",
+// other words match the name or a document's title and author
+pub fn matches(data: &FileData, query: &str) -> bool {
+    let document = data.document.as_ref();
+    let title = document.and_then(|d| d.title.as_deref());
+    let author = document.and_then(|d| d.author.as_deref());
+
+    query.split_whitespace().all(|word| {
+        let contains = |field: Option<&str>, value: &str| field.is_some_and(|f| collation_key(f).contains(&collation_key(value)));
+        match word.split_once(':') {
+            Some(("name", value)) => contains(Some(&data.name), value),
+            Some(("title", value)) => contains(title, value),
+            Some(("author", value)) => contains(author, value),
+            Some(("creator", value)) => contains(document.and_then(|d| d.creator.as_deref()), value),
+            Some(("pages", value)) => document.and_then(|d| d.pages).is_some_and(|p| p.to_string() == value),
+            _ => contains(Some(&data.name), word) || contains(title, word) || contains(author, word),
+        }
+    })
+}
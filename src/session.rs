@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sorting::Sort;
+
+// Everything needed to reopen the windows that were open when Filed was closed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub windows: Vec<WindowState>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowState {
+    // Width and height, the default window size is used when missing
+    pub size: Option<(f32, f32)>,
+    pub tabs: Vec<TabState>,
+    pub active: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TabState {
+    pub panes: Vec<PaneState>,
+    pub focused: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaneState {
+    pub history: Vec<PathBuf>,
+    pub history_index: usize,
+    pub sort: Sort,
+}
+
+impl WindowState {
+    // A window with a single tab at `path`
+    pub fn at(path: PathBuf) -> Self {
+        Self {
+            size: None,
+            tabs: vec![TabState {
+                panes: vec![PaneState { history: vec![path], ..PaneState::default() }],
+                focused: 0,
+            }],
+            active: 0,
+        }
+    }
+}
+
+impl Session {
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("filed").join("session.json"))
+    }
+
+    // An empty session when the file is missing or can't be parsed
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::path() else { return };
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        match serde_json::to_string_pretty(self) {
+            Ok(content) => {
+                if let Err(e) = std::fs::write(&path, content) {
+                    println!("Failed to save session: {}", e);
+                }
+            }
+            Err(e) => println!("Failed to serialize session: {}", e),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::columns::ColumnLayout;
+use crate::grouping::GroupBy;
+use crate::platform::Platform;
+use crate::sorting::Sort;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SortScope {
+    // One sort order is used for every directory
+    #[default]
+    Global,
+    // Each directory remembers the sort order it was last viewed with
+    PerDirectory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ViewMode {
+    #[default]
+    List,
+    Grid,
+    Columns,
+    Tree,
+}
+
+impl ViewMode {
+    pub const ALL: [ViewMode; 4] = [ViewMode::List, ViewMode::Tree, ViewMode::Grid, ViewMode::Columns];
+}
+
+impl std::fmt::Display for ViewMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            ViewMode::List => "List",
+            ViewMode::Grid => "Icons",
+            ViewMode::Columns => "Columns",
+            ViewMode::Tree => "Tree",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub sort_scope: SortScope,
+    pub sort: Sort,
+    pub directory_sorts: HashMap<PathBuf, Sort>,
+    pub folders_first: bool,
+    pub group_by: GroupBy,
+    pub columns: ColumnLayout,
+    pub view_mode: ViewMode,
+    // Tile size of the grid view in pixels
+    pub zoom: f32,
+    // Nodes of the sidebar folder tree that were left expanded
+    pub expanded_folders: Vec<PathBuf>,
+    // Shown in the sidebar below the special directories
+    pub bookmarks: Vec<PathBuf>,
+    // Reopen the windows and tabs of the last run, otherwise start fresh at `start_dir`
+    pub restore_session: bool,
+    pub start_dir: Option<PathBuf>,
+    pub show_preview: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            sort_scope: SortScope::Global,
+            sort: Sort::default(),
+            directory_sorts: HashMap::new(),
+            folders_first: true,
+            group_by: GroupBy::None,
+            columns: ColumnLayout::default(),
+            view_mode: ViewMode::List,
+            zoom: 96.0,
+            expanded_folders: vec![],
+            bookmarks: vec![],
+            restore_session: true,
+            start_dir: None,
+            show_preview: false,
+        }
+    }
+}
+
+impl Settings {
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("filed").join("settings.json"))
+    }
+
+    // Falls back to the defaults when the file is missing or can't be parsed
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::path() else { return };
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        match serde_json::to_string_pretty(self) {
+            Ok(content) => {
+                if let Err(e) = std::fs::write(&path, content) {
+                    println!("Failed to save settings: {}", e);
+                }
+            }
+            Err(e) => println!("Failed to serialize settings: {}", e),
+        }
+    }
+
+    // The home directory unless another existing one was chosen
+    pub fn start_dir(&self) -> PathBuf {
+        self.start_dir.clone().filter(|dir| dir.is_dir()).unwrap_or_else(Platform::home_dir)
+    }
+
+    pub fn sort_for(&self, path: &PathBuf) -> Sort {
+        match self.sort_scope {
+            SortScope::Global => self.sort.clone(),
+            SortScope::PerDirectory => self.directory_sorts.get(path).cloned().unwrap_or(self.sort.clone()),
+        }
+    }
+
+    pub fn set_sort_for(&mut self, path: &PathBuf, sort: Sort) {
+        match self.sort_scope {
+            SortScope::Global => self.sort = sort,
+            SortScope::PerDirectory => {
+                self.directory_sorts.insert(path.clone(), sort);
+            }
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::columns::ColumnKind;
+use crate::components::fileitem::{FileData, FileType};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortBy {
+    Name,
+    Size,
+    CreatedAt,
+    LastModified,
+    Extension,
+    FileType,
+    Accessed,
+    Permissions,
+    Owner,
+    Group,
+    MimeType,
+    Inode,
+    Links,
+    ItemCount,
+    DateTaken,
+    Title,
+    Artist,
+    Album,
+    Track,
+    Duration,
+    Bitrate,
+    Resolution,
+}
+
+impl SortBy {
+    pub const ALL: [SortBy; 22] = [
+        SortBy::Name,
+        SortBy::Size,
+        SortBy::CreatedAt,
+        SortBy::LastModified,
+        SortBy::Extension,
+        SortBy::FileType,
+        SortBy::Accessed,
+        SortBy::Permissions,
+        SortBy::Owner,
+        SortBy::Group,
+        SortBy::MimeType,
+        SortBy::Inode,
+        SortBy::Links,
+        SortBy::ItemCount,
+        SortBy::DateTaken,
+        SortBy::Title,
+        SortBy::Artist,
+        SortBy::Album,
+        SortBy::Track,
+        SortBy::Duration,
+        SortBy::Bitrate,
+        SortBy::Resolution,
+    ];
+}
+
+impl fmt::Display for SortBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            SortBy::Name => "Name",
+            SortBy::Size => "Size",
+            SortBy::CreatedAt => "Created At",
+            SortBy::LastModified => "Last Modified",
+            SortBy::Extension => "Extension",
+            SortBy::FileType => "Type",
+            SortBy::Accessed => "Accessed",
+            SortBy::Permissions => "Permissions",
+            SortBy::Owner => "Owner",
+            SortBy::Group => "Group",
+            SortBy::MimeType => "MIME Type",
+            SortBy::Inode => "Inode",
+            SortBy::Links => "Links",
+            SortBy::ItemCount => "Items",
+            SortBy::DateTaken => "Date Taken",
+            SortBy::Title => "Title",
+            SortBy::Artist => "Artist",
+            SortBy::Album => "Album",
+            SortBy::Track => "Track",
+            SortBy::Duration => "Duration",
+            SortBy::Bitrate => "Bitrate",
+            SortBy::Resolution => "Resolution",
+        })
+    }
+}
+
+fn default_secondary() -> SortBy {
+    SortBy::Name
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sort {
+    pub by: SortBy,
+    pub ascending: bool,
+    // Used when two entries are equal on `by`, always ascending
+    #[serde(default = "default_secondary")]
+    pub then_by: SortBy,
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Self {
+            by: SortBy::Name,
+            ascending: true,
+            then_by: SortBy::Name,
+        }
+    }
+}
+
+impl Sort {
+    // Clicking the active column flips the direction, any other column starts ascending
+    pub fn clicked(&self, by: SortBy) -> Self {
+        if self.by == by {
+            Self { ascending: !self.ascending, ..self.clone() }
+        } else {
+            Self { by, ascending: true, ..self.clone() }
+        }
+    }
+}
+
+// Lowercases and strips accents so "Émile" sorts next to "emile" rather than after "z"
+pub fn collation_key(s: &str) -> String {
+    s.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// Compares strings so that runs of digits are ordered by value, "file2" < "file10"
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+                let x_trimmed = x.trim_start_matches('0');
+                let y_trimmed = y.trim_start_matches('0');
+                let order = x_trimmed.len().cmp(&y_trimmed.len())
+                    .then_with(|| x_trimmed.cmp(y_trimmed))
+                    // "01" after "1"
+                    .then_with(|| x.len().cmp(&y.len()));
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut number = String::new();
+    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+        number.push(c);
+        chars.next();
+    }
+    number
+}
+
+fn compare_names(a: &str, b: &str) -> Ordering {
+    natural_cmp(&collation_key(a), &collation_key(b))
+        .then_with(|| a.cmp(b))
+}
+
+fn compare(a: &FileData, b: &FileData, by: &SortBy) -> Ordering {
+    match by {
+        SortBy::Name => compare_names(&a.name, &b.name),
+        SortBy::Size => a.size.unwrap_or(0).cmp(&b.size.unwrap_or(0)),
+        SortBy::CreatedAt => a.created.unwrap_or(SystemTime::UNIX_EPOCH).cmp(&b.created.unwrap_or(SystemTime::UNIX_EPOCH)),
+        SortBy::LastModified => a.last_modified.unwrap_or(SystemTime::UNIX_EPOCH).cmp(&b.last_modified.unwrap_or(SystemTime::UNIX_EPOCH)),
+        SortBy::Extension => natural_cmp(&a.extension(), &b.extension()),
+        SortBy::FileType => a.file_type.clone().unwrap_or(FileType::Unknown).cmp(&b.file_type.clone().unwrap_or(FileType::Unknown)),
+        SortBy::Accessed => a.accessed.unwrap_or(SystemTime::UNIX_EPOCH).cmp(&b.accessed.unwrap_or(SystemTime::UNIX_EPOCH)),
+        SortBy::Permissions => a.unix.map(|u| u.mode & 0o7777).cmp(&b.unix.map(|u| u.mode & 0o7777)),
+        SortBy::Owner => ColumnKind::Owner.value(a).cmp(&ColumnKind::Owner.value(b)),
+        SortBy::Group => ColumnKind::Group.value(a).cmp(&ColumnKind::Group.value(b)),
+        SortBy::MimeType => ColumnKind::MimeType.value(a).cmp(&ColumnKind::MimeType.value(b)),
+        SortBy::Inode => a.unix.map(|u| u.inode).cmp(&b.unix.map(|u| u.inode)),
+        SortBy::Links => a.unix.map(|u| u.links).cmp(&b.unix.map(|u| u.links)),
+        SortBy::ItemCount => a.item_count.cmp(&b.item_count),
+        SortBy::DateTaken => a.taken.cmp(&b.taken),
+        SortBy::Title => compare_names(&ColumnKind::Title.value(a), &ColumnKind::Title.value(b)),
+        SortBy::Artist => compare_names(&ColumnKind::Artist.value(a), &ColumnKind::Artist.value(b)),
+        SortBy::Album => compare_names(&ColumnKind::Album.value(a), &ColumnKind::Album.value(b)),
+        SortBy::Track => a.tags.as_ref().and_then(|t| t.track).cmp(&b.tags.as_ref().and_then(|t| t.track)),
+        SortBy::Duration => a.duration().cmp(&b.duration()),
+        SortBy::Bitrate => a.tags.as_ref().and_then(|t| t.bitrate).cmp(&b.tags.as_ref().and_then(|t| t.bitrate)),
+        // By the number of pixels
+        SortBy::Resolution => pixels(a).cmp(&pixels(b)),
+    }
+}
+
+fn pixels(data: &FileData) -> Option<u64> {
+    data.video.as_ref()?.resolution.map(|(w, h)| w as u64 * h as u64)
+}
+
+pub fn sort_files(tree: &mut [FileData], sort: &Sort, folders_first: bool) {
+    tree.sort_by(|a, b| {
+        let order = compare(a, b, &sort.by);
+        let order = if sort.ascending { order } else { order.reverse() };
+        let order = order
+            .then_with(|| compare(a, b, &sort.then_by))
+            .then_with(|| compare_names(&a.name, &b.name));
+        if folders_first {
+            b.is_dir.cmp(&a.is_dir).then(order)
+        } else {
+            order
+        }
+    });
+}
@@ -0,0 +1,133 @@
+// JSON, YAML and TOML documents turned into one tree the preview can show
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "json" | "geojson" | "jsonc" => Some(Format::Json),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "toml" => Some(Format::Toml),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Format::Json => "JSON",
+            Format::Yaml => "YAML",
+            Format::Toml => "TOML",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarKind {
+    Null,
+    Bool,
+    Number,
+    String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Node {
+    Scalar(ScalarKind, String),
+    Array(Vec<Node>),
+    // Keys in the order the parser gives them
+    Object(Vec<(String, Node)>),
+}
+
+impl Node {
+    pub fn len(&self) -> usize {
+        match self {
+            Node::Scalar(..) => 0,
+            Node::Array(items) => items.len(),
+            Node::Object(entries) => entries.len(),
+        }
+    }
+
+    // Children with the key they're shown under, array items use their index
+    pub fn children(&self) -> Vec<(String, &Node)> {
+        match self {
+            Node::Scalar(..) => vec![],
+            Node::Array(items) => items.iter().enumerate().map(|(i, node)| (i.to_string(), node)).collect(),
+            Node::Object(entries) => entries.iter().map(|(key, node)| (key.clone(), node)).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub message: String,
+    // 1-based, if the parser knows where it failed
+    pub line: Option<usize>,
+}
+
+pub fn parse(text: &str, format: Format) -> Result<Node, ParseError> {
+    match format {
+        Format::Json => serde_json::from_str::<serde_json::Value>(text)
+            .map(from_json)
+            .map_err(|e| ParseError { message: e.to_string(), line: Some(e.line()).filter(|l| *l > 0) }),
+        Format::Yaml => serde_yaml::from_str::<serde_yaml::Value>(text)
+            .map(from_yaml)
+            .map_err(|e| ParseError { message: e.to_string(), line: e.location().map(|l| l.line()) }),
+        Format::Toml => text.parse::<toml::Table>()
+            .map(|table| from_toml(toml::Value::Table(table)))
+            .map_err(|e| ParseError {
+                message: e.message().to_owned(),
+                line: e.span().map(|span| text[..span.start.min(text.len())].matches('\n').count() + 1),
+            }),
+    }
+}
+
+fn from_json(value: serde_json::Value) -> Node {
+    use serde_json::Value;
+    match value {
+        Value::Null => Node::Scalar(ScalarKind::Null, "null".to_owned()),
+        Value::Bool(b) => Node::Scalar(ScalarKind::Bool, b.to_string()),
+        Value::Number(n) => Node::Scalar(ScalarKind::Number, n.to_string()),
+        Value::String(s) => Node::Scalar(ScalarKind::String, s),
+        Value::Array(items) => Node::Array(items.into_iter().map(from_json).collect()),
+        Value::Object(map) => Node::Object(map.into_iter().map(|(k, v)| (k, from_json(v))).collect()),
+    }
+}
+
+fn from_yaml(value: serde_yaml::Value) -> Node {
+    use serde_yaml::Value;
+    match value {
+        Value::Null => Node::Scalar(ScalarKind::Null, "null".to_owned()),
+        Value::Bool(b) => Node::Scalar(ScalarKind::Bool, b.to_string()),
+        Value::Number(n) => Node::Scalar(ScalarKind::Number, n.to_string()),
+        Value::String(s) => Node::Scalar(ScalarKind::String, s),
+        Value::Sequence(items) => Node::Array(items.into_iter().map(from_yaml).collect()),
+        Value::Mapping(map) => Node::Object(map.into_iter().map(|(k, v)| (yaml_key(k), from_yaml(v))).collect()),
+        Value::Tagged(tagged) => from_yaml(tagged.value),
+    }
+}
+
+fn yaml_key(key: serde_yaml::Value) -> String {
+    match key {
+        serde_yaml::Value::String(s) => s,
+        other => serde_yaml::to_string(&other).map(|s| s.trim().to_owned()).unwrap_or_default(),
+    }
+}
+
+fn from_toml(value: toml::Value) -> Node {
+    use toml::Value;
+    match value {
+        Value::String(s) => Node::Scalar(ScalarKind::String, s),
+        Value::Integer(i) => Node::Scalar(ScalarKind::Number, i.to_string()),
+        Value::Float(f) => Node::Scalar(ScalarKind::Number, f.to_string()),
+        Value::Boolean(b) => Node::Scalar(ScalarKind::Bool, b.to_string()),
+        Value::Datetime(d) => Node::Scalar(ScalarKind::String, d.to_string()),
+        Value::Array(items) => Node::Array(items.into_iter().map(from_toml).collect()),
+        Value::Table(table) => Node::Object(table.into_iter().map(|(k, v)| (k, from_toml(v))).collect()),
+    }
+}
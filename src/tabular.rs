@@ -0,0 +1,111 @@
+// Delimiters tried when sniffing, in order of preference
+const DELIMITERS: [char; 4] = [',', '\t', ';', '|'];
+
+// Lines looked at to guess the delimiter and the header
+const SAMPLE_LINES: usize = 20;
+
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub delimiter: char,
+    pub header: Option<Vec<String>>,
+    pub rows: Vec<Vec<String>>,
+}
+
+// Splits CSV/TSV text into rows, `limit` caps how many rows are kept
+pub fn parse(text: &str, tsv: bool, limit: usize) -> (Table, bool) {
+    let delimiter = if tsv { '\t' } else { sniff(text) };
+    let mut rows = records(text, delimiter, limit + 1);
+    let truncated = rows.len() > limit;
+    rows.truncate(limit);
+
+    let header = if has_header(&rows) { Some(rows.remove(0)) } else { None };
+    (Table { delimiter, header, rows }, truncated)
+}
+
+// The delimiter that splits the sample lines into the same number of fields most often
+fn sniff(text: &str) -> char {
+    let sample = text.lines().take(SAMPLE_LINES).collect::<Vec<_>>().join("\n");
+
+    let mut best = (',', 0);
+    for delimiter in DELIMITERS {
+        let rows = records(&sample, delimiter, SAMPLE_LINES);
+        let Some(first) = rows.first().map(|r| r.len()) else { continue };
+        if first < 2 {
+            continue;
+        }
+        let consistent = rows.iter().filter(|r| r.len() == first).count();
+        if consistent > best.1 {
+            best = (delimiter, consistent);
+        }
+    }
+    best.0
+}
+
+// The first row is a header if its cells look different from the rest of their column,
+// like a name above numbers. Without numeric columns unique non-empty names count as a header
+fn has_header(rows: &[Vec<String>]) -> bool {
+    let Some((first, rest)) = rows.split_first() else { return false };
+    if rest.is_empty() {
+        return false;
+    }
+
+    let mut votes = 0;
+    for (i, cell) in first.iter().enumerate() {
+        let column = rest.iter().filter_map(|row| row.get(i)).filter(|c| !c.is_empty()).collect::<Vec<_>>();
+        if column.is_empty() {
+            continue;
+        }
+        if column.iter().all(|c| is_number(c)) {
+            votes += if is_number(cell) { -1 } else { 1 };
+        }
+    }
+    if votes != 0 {
+        return votes > 0;
+    }
+
+    let mut names = first.iter().filter(|c| !c.is_empty() && !is_number(c)).collect::<Vec<_>>();
+    let count = names.len();
+    names.sort();
+    names.dedup();
+    count == first.len() && names.len() == count
+}
+
+fn is_number(cell: &str) -> bool {
+    cell.trim().parse::<f64>().is_ok()
+}
+
+// RFC 4180 style: quoted fields may contain delimiters, newlines and "" for a quote
+fn records(text: &str, delimiter: char, limit: usize) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if rows.len() >= limit {
+            return rows;
+        }
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            c if c == delimiter && !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.truncate(limit);
+    rows
+}
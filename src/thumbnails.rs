@@ -0,0 +1,186 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::UNIX_EPOCH;
+
+use iced::futures::channel::oneshot;
+use iced::widget::image::Handle;
+use iced::Task;
+use image::RgbaImage;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::photo;
+
+// Thumbnails are generated once at this size and scaled down by the view
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+// Sub directory of `fail/` holding the files we could not thumbnail, the spec wants it versioned
+const FAIL_DIR: &str = concat!("filed-", env!("CARGO_PKG_VERSION"));
+
+// Directories of the freedesktop thumbnail cache, largest first
+const SIZES: [(&str, u32); 2] = [("large", 256), ("normal", 128)];
+
+fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("thumbnails"))
+}
+
+// `file://` URI of an absolute path escaped like GLib's `g_filename_to_uri`, which the other
+// apps sharing the cache use. The MD5 of it only matches if the same characters are left alone
+fn file_uri(path: &Path) -> String {
+    #[cfg(unix)]
+    let bytes = {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes().to_vec()
+    };
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().replace('\\', "/").into_bytes();
+
+    let mut uri = String::from("file://");
+    if bytes.first() != Some(&b'/') {
+        uri.push('/');
+    }
+    for byte in bytes {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/'
+            | b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b'=' | b':' | b'@' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+// Cache entries are named after the MD5 of the URI
+fn cache_name(uri: &str) -> String {
+    format!("{:x}.png", md5::compute(uri.as_bytes()))
+}
+
+fn mtime(path: &Path) -> Option<u64> {
+    path.metadata().ok()?
+        .modified().ok()?
+        .duration_since(UNIX_EPOCH).ok()
+        .map(|d| d.as_secs())
+}
+
+// A cached thumbnail is only valid if it was made from the same URI at the same mtime
+fn is_valid(bytes: &[u8], uri: &str, mtime: u64) -> bool {
+    let Ok(mut reader) = png::Decoder::new(bytes).read_info() else { return false };
+    // The text chunks may come after the image data
+    let mut buf = vec![0; reader.output_buffer_size()];
+    if reader.next_frame(&mut buf).is_err() || reader.finish().is_err() {
+        return false;
+    }
+
+    let text = &reader.info().uncompressed_latin1_text;
+    let value = |key: &str| text.iter().find(|chunk| chunk.keyword == key).map(|chunk| chunk.text.clone());
+    value("Thumb::URI").as_deref() == Some(uri) && value("Thumb::MTime").and_then(|m| m.parse().ok()) == Some(mtime)
+}
+
+fn read_cached(path: &Path, uri: &str, mtime: u64) -> Option<RgbaImage> {
+    let bytes = std::fs::read(path).ok()?;
+    if !is_valid(&bytes, uri, mtime) {
+        return None;
+    }
+    image::load_from_memory(&bytes).ok().map(|image| image.to_rgba8())
+}
+
+fn write_png(path: &Path, image: &RgbaImage, uri: &str, mtime: u64) -> Option<()> {
+    std::fs::create_dir_all(path.parent()?).ok()?;
+
+    // Written next to the target and renamed so other apps never read a partial file
+    let temp = path.with_extension(format!("png.{}.tmp", std::process::id()));
+    let file = File::create(&temp).ok()?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = file.set_permissions(std::fs::Permissions::from_mode(0o600));
+    }
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.add_text_chunk("Thumb::URI".to_owned(), uri.to_owned()).ok()?;
+    encoder.add_text_chunk("Thumb::MTime".to_owned(), mtime.to_string()).ok()?;
+    encoder.add_text_chunk("Software".to_owned(), "Filed".to_owned()).ok()?;
+
+    let result = encoder.write_header()
+        .and_then(|mut writer| writer.write_image_data(image.as_raw()));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+        return None;
+    }
+    std::fs::rename(&temp, path).ok()
+}
+
+pub fn generate(path: &PathBuf, size: u32) -> Option<Handle> {
+    let image = cached_or_generate(path, size)?;
+    let (width, height) = image.dimensions();
+    Some(Handle::from_rgba(width, height, image.into_raw()))
+}
+
+fn cached_or_generate(path: &PathBuf, size: u32) -> Option<RgbaImage> {
+    let path = path.canonicalize().ok()?;
+    let Some(cache) = cache_dir() else {
+        return photo::open(&path).map(|image| image.thumbnail(size, size).to_rgba8());
+    };
+    let uri = file_uri(&path);
+    let name = cache_name(&uri);
+    let mtime = mtime(&path)?;
+
+    for (dir, _) in SIZES.iter().filter(|(_, s)| *s >= size).chain(SIZES.iter().filter(|(_, s)| *s < size)) {
+        if let Some(image) = read_cached(&cache.join(dir).join(&name), &uri, mtime) {
+            return Some(image);
+        }
+    }
+
+    let failed = cache.join("fail").join(FAIL_DIR).join(&name);
+    if read_cached(&failed, &uri, mtime).is_some() {
+        return None;
+    }
+
+    // Turned upright first, the cached thumbnail carries no orientation
+    let Some(image) = photo::open(&path) else {
+        // An empty image marks the failure so no one tries again until the file changes
+        write_png(&failed, &RgbaImage::new(1, 1), &uri, mtime);
+        return None;
+    };
+
+    let mut result = None;
+    for (dir, dir_size) in SIZES {
+        let thumbnail = image.thumbnail(dir_size, dir_size).to_rgba8();
+        write_png(&cache.join(dir).join(&name), &thumbnail, &uri, mtime);
+        if result.is_none() && dir_size <= size.max(SIZES[1].1) {
+            result = Some(thumbnail);
+        }
+    }
+    result
+}
+
+// Thumbnails get their own threads so they never hold up directory loads and previews on the global pool
+fn pool() -> &'static ThreadPool {
+    static POOL: OnceLock<ThreadPool> = OnceLock::new();
+    POOL.get_or_init(|| {
+        ThreadPoolBuilder::new()
+            .num_threads(std::thread::available_parallelism().map_or(2, |n| n.get().div_ceil(2)))
+            .thread_name(|i| format!("thumbnails-{}", i))
+            .build()
+            .expect("Failed to start the thumbnail threads")
+    })
+}
+
+// Decodes the image off the executor so large photos never block it.
+// Jobs still queued once `cancelled` is set are skipped
+pub fn request(path: PathBuf, cancelled: Arc<AtomicBool>) -> Task<(PathBuf, Option<Handle>)> {
+    let (sender, receiver) = oneshot::channel();
+
+    pool().spawn(move || {
+        if cancelled.load(Ordering::Relaxed) {
+            return;
+        }
+        let thumbnail = generate(&path, THUMBNAIL_SIZE);
+        let _ = sender.send((path, thumbnail));
+    });
+
+    Task::perform(receiver, |result| result.unwrap_or((PathBuf::new(), None)))
+}
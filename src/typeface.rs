@@ -0,0 +1,568 @@
+use std::io::Read;
+use std::path::Path;
+
+use ttf_parser::{name_id, Face};
+
+// Font files bigger than this aren't previewed
+const MAX_FONT_SIZE: u64 = 64 * 1024 * 1024;
+
+// A character to look for in the font for each script, with a sample to render
+const SCRIPTS: [(&str, char, &str); 14] = [
+    ("Latin", 'A', "The quick brown fox jumps over the lazy dog"),
+    ("Greek", 'Ω', "Ταχίστη αλώπηξ βαφής ψημένη γη"),
+    ("Cyrillic", 'Ж', "Съешь же ещё этих мягких булок"),
+    ("Armenian", 'Ա', "Բարև աշխարհ"),
+    ("Hebrew", 'א', "דג סקרן שט בים מאוכזב"),
+    ("Arabic", 'ب', "نص حكيم له سر قاطع"),
+    ("Devanagari", 'क', "ऋषियों को सताने वाले"),
+    ("Thai", 'ก', "เป็นมนุษย์สุดประเสริฐ"),
+    ("Georgian", 'ა', "გამარჯობა მსოფლიო"),
+    ("Hangul", '한', "다람쥐 헌 쳇바퀴에 타고파"),
+    ("Hiragana", 'あ', "いろはにほへと ちりぬるを"),
+    ("Katakana", 'ア', "イロハニホヘト チリヌルヲ"),
+    ("Han", '中', "天地玄黄 宇宙洪荒"),
+    ("Symbols", '→', "← ↑ → ↓ ★ ♥ ✓"),
+];
+
+// What the preview shows of a font file
+#[derive(Debug, Clone)]
+pub struct FontInfo {
+    pub family: String,
+    pub style: String,
+    pub glyphs: u16,
+    pub scripts: Vec<&'static str>,
+    // Usually 100 to 900, 400 being regular
+    pub weight: u16,
+    pub italic: bool,
+    // Text written in the first script the font covers
+    pub sample: &'static str,
+}
+
+impl FontInfo {
+    // Label and value of every known field, in the order they're shown
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("Family", self.family.clone()),
+            ("Style", self.style.clone()),
+            ("Glyphs", self.glyphs.to_string()),
+            ("Scripts", if self.scripts.is_empty() { "-".to_owned() } else { self.scripts.join(", ") }),
+        ]
+    }
+}
+
+pub fn is_font(extension: &str) -> bool {
+    matches!(extension, "ttf" | "otf" | "woff2")
+}
+
+// The font as TrueType/OpenType data, WOFF2 is unpacked. None if it can't be read.
+// `renderable` is false if the data is only good for reading the font's names
+pub fn load(path: &Path) -> Option<(FontInfo, Vec<u8>, bool)> {
+    let mut data = vec![];
+    std::fs::File::open(path).ok()?.take(MAX_FONT_SIZE).read_to_end(&mut data).ok()?;
+    let (data, renderable) = if data.starts_with(b"wOF2") { woff2(&data)? } else { (data, true) };
+    let info = info(&data)?;
+    Some((info, data, renderable))
+}
+
+fn info(data: &[u8]) -> Option<FontInfo> {
+    let face = Face::parse(data, 0).ok()?;
+    // Typographic names group all weights of a family under one name, like font matching does
+    let name = |ids: &[u16]| {
+        ids.iter().find_map(|id| {
+            face.names().into_iter()
+                .filter(|name| name.name_id == *id && name.is_unicode())
+                .find_map(|name| name.to_string())
+        })
+    };
+    let family = name(&[name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY])?;
+    let scripts = SCRIPTS.iter()
+        .filter(|(_, c, _)| face.glyph_index(*c).is_some())
+        .collect::<Vec<_>>();
+
+    Some(FontInfo {
+        family,
+        style: name(&[name_id::TYPOGRAPHIC_SUBFAMILY, name_id::SUBFAMILY]).unwrap_or("Regular".to_owned()),
+        glyphs: face.number_of_glyphs(),
+        scripts: scripts.iter().map(|(script, _, _)| *script).collect(),
+        weight: face.weight().to_number(),
+        italic: face.is_italic(),
+        sample: scripts.first().map(|(_, _, sample)| *sample).unwrap_or(SCRIPTS[0].2),
+    })
+}
+
+// Tags of the tables WOFF2 refers to by index
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm", b"glyf",
+    b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern", b"LTSH", b"PCLT",
+    b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC", b"JSTF", b"MATH", b"CBDT",
+    b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar", b"bdat", b"bloc", b"bsln", b"cvar",
+    b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty", b"just", b"lcar", b"mort", b"morx", b"opbd",
+    b"prop", b"trak", b"Zapf", b"Silf", b"Glat", b"Gloc", b"Feat", b"Sill",
+];
+
+// Decompresses the tables and puts them back into an sfnt, rebuilding transformed glyf, loca
+// and hmtx tables. Fonts with a table that can't be rebuilt get their names read but aren't rendered
+fn woff2(data: &[u8]) -> Option<(Vec<u8>, bool)> {
+    let flavor = data.get(4..8)?;
+    // Collections have a different directory
+    if flavor == b"ttcf" {
+        return None;
+    }
+    let count = u16::from_be_bytes(data.get(12..14)?.try_into().ok()?) as usize;
+    let compressed = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?) as usize;
+
+    let mut offset = 48;
+    let mut directory = vec![];
+    for _ in 0..count {
+        let flags = *data.get(offset)?;
+        offset += 1;
+        let tag: [u8; 4] = match flags & 0x3f {
+            63 => {
+                offset += 4;
+                data.get(offset - 4..offset)?.try_into().ok()?
+            }
+            index => *KNOWN_TAGS[index as usize],
+        };
+        let version = flags >> 6;
+        let length = base128(data, &mut offset)?;
+        // glyf and loca are transformed unless the version is 3, other tables if it isn't 0
+        let transformed = if &tag == b"glyf" || &tag == b"loca" { version != 3 } else { version != 0 };
+        let stored = if transformed { base128(data, &mut offset)? } else { length };
+        directory.push((tag, stored as usize, transformed));
+    }
+
+    let mut tables = vec![];
+    brotli_decompressor::Decompressor::new(data.get(offset..offset.checked_add(compressed)?)?, 4096)
+        .take(MAX_FONT_SIZE)
+        .read_to_end(&mut tables)
+        .ok()?;
+
+    let mut position: usize = 0;
+    let mut entries = vec![];
+    for (tag, length, transformed) in directory {
+        let table = tables.get(position..position.checked_add(length)?)?;
+        position += length;
+        entries.push((tag, table, transformed));
+    }
+
+    // The transformed glyf table holds loca too, hmtx needs the glyphs' bounding boxes
+    let table = |tag: &[u8; 4]| entries.iter().find(|(t, _, _)| t == tag).map(|(_, table, transformed)| (*table, *transformed));
+    let glyphs = match table(b"glyf") {
+        Some((glyf, true)) => reconstruct_glyf(glyf),
+        _ => None,
+    };
+    let metrics = match (table(b"hmtx"), &glyphs) {
+        (Some((hmtx, true)), Some(glyphs)) => table(b"hhea")
+            .and_then(|(hhea, _)| hhea.get(34..36))
+            .and_then(|count| reconstruct_hmtx(hmtx, &glyphs.x_mins, u16::from_be_bytes([count[0], count[1]]) as usize)),
+        _ => None,
+    };
+
+    let mut renderable = true;
+    let mut kept = vec![];
+    for (tag, table, transformed) in &entries {
+        let table = match (tag, transformed) {
+            (_, false) => Some(*table),
+            (b"glyf", true) => glyphs.as_ref().map(|glyphs| &glyphs.glyf[..]),
+            (b"loca", true) => glyphs.as_ref().map(|glyphs| &glyphs.loca[..]),
+            (b"hmtx", true) => metrics.as_deref(),
+            _ => None,
+        };
+        match table {
+            Some(table) => kept.push((*tag, table)),
+            None => renderable = false,
+        }
+    }
+    Some((sfnt(flavor, &mut kept), renderable))
+}
+
+// A position in one of the streams the transformed tables are split into
+struct Stream<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Stream<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.offset..self.offset.checked_add(len)?)?;
+        self.offset += len;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_be_bytes(self.bytes(2)?.try_into().ok()?))
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        Some(i16::from_be_bytes(self.bytes(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    // "255UInt16", values below 253 take a single byte
+    fn u16_255(&mut self) -> Option<u16> {
+        match self.u8()? {
+            253 => self.u16(),
+            254 => Some(506 + self.u8()? as u16),
+            255 => Some(253 + self.u8()? as u16),
+            code => Some(code as u16),
+        }
+    }
+}
+
+struct Glyphs {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    // Left edge of every glyph's bounding box, 0 for empty ones
+    x_mins: Vec<i16>,
+}
+
+// Rebuilds glyf and loca from the transformed glyf table, section 5 of the WOFF2 spec.
+// Glyphs are written out again with the plain, unpacked flags and coordinates of TrueType
+fn reconstruct_glyf(table: &[u8]) -> Option<Glyphs> {
+    let mut header = Stream::new(table);
+    header.u16()?;
+    let options = header.u16()?;
+    let glyph_count = header.u16()? as usize;
+    let index_format = header.u16()?;
+
+    // Seven streams follow the header, each glyph takes its part of every one of them
+    let mut streams = vec![];
+    let mut start: usize = 36;
+    for _ in 0..7 {
+        let end = start.checked_add(header.u32()? as usize)?;
+        streams.push(Stream::new(table.get(start..end)?));
+        start = end;
+    }
+    let [mut contours, mut points, mut flags, mut glyph_data, mut composites, mut bboxes, mut instructions] = streams.try_into().ok()?;
+    // Simple glyphs whose contours overlap, only there if the first option bit is set
+    let overlaps = if options & 1 != 0 { table.get(start..start.checked_add(glyph_count.div_ceil(8))?) } else { None };
+    let bbox_bitmap = bboxes.bytes(glyph_count.div_ceil(32) * 4)?;
+    let is_set = |bitmap: &[u8], glyph: usize| bitmap[glyph >> 3] & (0x80 >> (glyph & 7)) != 0;
+
+    let mut glyf = vec![];
+    let mut offsets = Vec::with_capacity(glyph_count + 1);
+    let mut x_mins = Vec::with_capacity(glyph_count);
+    for glyph in 0..glyph_count {
+        offsets.push(glyf.len());
+        let contour_count = contours.i16()?;
+        let bbox = if is_set(bbox_bitmap, glyph) {
+            Some([bboxes.i16()?, bboxes.i16()?, bboxes.i16()?, bboxes.i16()?])
+        } else {
+            None
+        };
+
+        if contour_count == 0 {
+            x_mins.push(0);
+        } else if contour_count == -1 {
+            // Components are copied as they are, only their instructions are kept apart.
+            // The bounding box of a composite is always stored
+            let bbox = bbox?;
+            let start = composites.offset;
+            let mut has_instructions = false;
+            loop {
+                let flags = composites.u16()?;
+                // Glyph index, the two arguments and the scale or matrix if there is one
+                let arguments = if flags & 0x0001 != 0 { 4 } else { 2 };
+                let transform = if flags & 0x0008 != 0 { 2 } else if flags & 0x0040 != 0 { 4 } else if flags & 0x0080 != 0 { 8 } else { 0 };
+                composites.bytes(2 + arguments + transform)?;
+                has_instructions |= flags & 0x0100 != 0;
+                if flags & 0x0020 == 0 {
+                    break;
+                }
+            }
+            glyf.extend_from_slice(&contour_count.to_be_bytes());
+            bbox.iter().for_each(|value| glyf.extend_from_slice(&value.to_be_bytes()));
+            glyf.extend_from_slice(&composites.data[start..composites.offset]);
+            if has_instructions {
+                let len = glyph_data.u16_255()?;
+                glyf.extend_from_slice(&len.to_be_bytes());
+                glyf.extend_from_slice(instructions.bytes(len as usize)?);
+            }
+            x_mins.push(bbox[0]);
+        } else if contour_count > 0 {
+            let mut end_points = vec![];
+            let mut point_count: usize = 0;
+            for _ in 0..contour_count {
+                point_count = point_count.checked_add(points.u16_255()? as usize)?;
+                end_points.push(u16::try_from(point_count.checked_sub(1)?).ok()?);
+            }
+            let point_flags = flags.bytes(point_count)?;
+            let deltas = point_flags.iter().map(|flag| triplet(flag & 0x7f, &mut glyph_data)).collect::<Option<Vec<_>>>()?;
+            let instruction_len = glyph_data.u16_255()?;
+            let program = instructions.bytes(instruction_len as usize)?;
+
+            // Without a stored bounding box it's the one around the points
+            let bbox = bbox.unwrap_or_else(|| {
+                let (mut x, mut y) = (0i32, 0i32);
+                let mut bbox = [i32::MAX, i32::MAX, i32::MIN, i32::MIN];
+                for (dx, dy) in &deltas {
+                    (x, y) = (x + dx, y + dy);
+                    bbox = [bbox[0].min(x), bbox[1].min(y), bbox[2].max(x), bbox[3].max(y)];
+                }
+                bbox.map(|value| value as i16)
+            });
+
+            glyf.extend_from_slice(&contour_count.to_be_bytes());
+            bbox.iter().for_each(|value| glyf.extend_from_slice(&value.to_be_bytes()));
+            end_points.iter().for_each(|end| glyf.extend_from_slice(&end.to_be_bytes()));
+            glyf.extend_from_slice(&instruction_len.to_be_bytes());
+            glyf.extend_from_slice(program);
+
+            let mut xs = vec![];
+            let mut ys = vec![];
+            for (i, (flag, (dx, dy))) in point_flags.iter().zip(deltas).enumerate() {
+                // The high bit marks points off the curve, TrueType sets one for points on it
+                let mut out = if flag & 0x80 == 0 { 0x01 } else { 0 };
+                if i == 0 && overlaps.is_some_and(|bitmap| is_set(bitmap, glyph)) {
+                    out |= 0x40;
+                }
+                out |= coordinate(dx, 0x02, 0x10, &mut xs) | coordinate(dy, 0x04, 0x20, &mut ys);
+                glyf.push(out);
+            }
+            glyf.extend_from_slice(&xs);
+            glyf.extend_from_slice(&ys);
+            x_mins.push(bbox[0]);
+        } else {
+            return None;
+        }
+        // The short loca format needs even offsets, 4 keeps every glyph aligned
+        glyf.resize(glyf.len().div_ceil(4) * 4, 0);
+    }
+    offsets.push(glyf.len());
+
+    let loca = match index_format {
+        0 => offsets.iter().map(|offset| u16::try_from(offset / 2).ok().map(u16::to_be_bytes)).collect::<Option<Vec<_>>>()?.concat(),
+        _ => offsets.iter().map(|offset| u32::try_from(*offset).ok().map(u32::to_be_bytes)).collect::<Option<Vec<_>>>()?.concat(),
+    };
+    Some(Glyphs { glyf, loca, x_mins })
+}
+
+// A point's offset from the previous one, packed by the flag's low 7 bits into 1 to 4 bytes
+fn triplet(flag: u8, data: &mut Stream) -> Option<(i32, i32)> {
+    // Bit 0 of the flag is the sign of x, or of y when x is 0, bit 1 the sign of y
+    let sign = |bit: u8, value: i32| if bit & 1 != 0 { value } else { -value };
+    let flag32 = flag as i32;
+    let delta = match flag {
+        0..10 => (0, sign(flag, ((flag32 & 14) << 7) + data.u8()? as i32)),
+        10..20 => (sign(flag, (((flag32 - 10) & 14) << 7) + data.u8()? as i32), 0),
+        20..84 => {
+            let (b0, b1) = (flag32 - 20, data.u8()? as i32);
+            (sign(flag, 1 + (b0 & 0x30) + (b1 >> 4)), sign(flag >> 1, 1 + ((b0 & 0x0c) << 2) + (b1 & 0x0f)))
+        }
+        84..120 => {
+            let (b0, b1, b2) = (flag32 - 84, data.u8()? as i32, data.u8()? as i32);
+            (sign(flag, 1 + ((b0 / 12) << 8) + b1), sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b2))
+        }
+        120..124 => {
+            let (b1, b2, b3) = (data.u8()? as i32, data.u8()? as i32, data.u8()? as i32);
+            (sign(flag, (b1 << 4) + (b2 >> 4)), sign(flag >> 1, ((b2 & 0x0f) << 8) + b3))
+        }
+        _ => {
+            let (x, y) = (data.u16()? as i32, data.u16()? as i32);
+            (sign(flag, x), sign(flag >> 1, y))
+        }
+    };
+    Some(delta)
+}
+
+// Writes a TrueType coordinate delta and returns its flags, `short` is set for one byte deltas
+// and `same` is their sign, or means the delta is 0 when `short` isn't set
+fn coordinate(delta: i32, short: u8, same: u8, out: &mut Vec<u8>) -> u8 {
+    if delta == 0 {
+        same
+    } else if delta.abs() < 256 {
+        out.push(delta.unsigned_abs() as u8);
+        if delta > 0 { short | same } else { short }
+    } else {
+        // Coordinates are 16 bit, a delta across the whole range wraps like in the original font
+        out.extend_from_slice(&(delta as i16).to_be_bytes());
+        0
+    }
+}
+
+// Rebuilds hmtx from its transformed version, section 5.4 of the WOFF2 spec.
+// Left side bearings that were left out are the xMin of the glyphs
+fn reconstruct_hmtx(table: &[u8], x_mins: &[i16], metric_count: usize) -> Option<Vec<u8>> {
+    let mut data = Stream::new(table);
+    let flags = data.u8()?;
+    let advances = (0..metric_count).map(|_| data.u16()).collect::<Option<Vec<_>>>()?;
+    let bearings = match flags & 1 {
+        0 => (0..metric_count).map(|_| data.i16()).collect::<Option<Vec<_>>>()?,
+        _ => x_mins.get(..metric_count)?.to_vec(),
+    };
+    // Glyphs after the last metric share its advance and only have a bearing
+    let monospaced = match flags & 2 {
+        0 => (metric_count..x_mins.len()).map(|_| data.i16()).collect::<Option<Vec<_>>>()?,
+        _ => x_mins.get(metric_count..)?.to_vec(),
+    };
+
+    let mut hmtx = vec![];
+    for (advance, bearing) in advances.iter().zip(&bearings) {
+        hmtx.extend_from_slice(&advance.to_be_bytes());
+        hmtx.extend_from_slice(&bearing.to_be_bytes());
+    }
+    monospaced.iter().for_each(|bearing| hmtx.extend_from_slice(&bearing.to_be_bytes()));
+    Some(hmtx)
+}
+
+// "UIntBase128", 7 bits per byte with the high bit set on all but the last
+fn base128(data: &[u8], offset: &mut usize) -> Option<u32> {
+    let mut value: u32 = 0;
+    for _ in 0..5 {
+        let byte = *data.get(*offset)?;
+        *offset += 1;
+        if value & 0xfe00_0000 != 0 {
+            return None;
+        }
+        value = (value << 7) | (byte & 0x7f) as u32;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+// An OpenType file from its tables. Checksums are left at 0, nothing we load the font with checks them
+fn sfnt(flavor: &[u8], tables: &mut [([u8; 4], &[u8])]) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| *tag);
+    // The biggest power of two not above the table count, in u32 so `* 16` can't overflow
+    let count = tables.len() as u32;
+    let power = if count == 0 { 0 } else { 1 << count.ilog2() };
+    let search_range = power * 16;
+
+    let mut font = vec![];
+    font.extend_from_slice(flavor);
+    for value in [count, search_range, power.checked_ilog2().unwrap_or(0), count * 16 - search_range] {
+        font.extend_from_slice(&(value as u16).to_be_bytes());
+    }
+
+    let mut offset = 12 + tables.len() * 16;
+    for (tag, table) in tables.iter() {
+        font.extend_from_slice(tag);
+        font.extend_from_slice(&0u32.to_be_bytes());
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        offset += table.len().div_ceil(4) * 4;
+    }
+    for (_, table) in tables.iter() {
+        font.extend_from_slice(table);
+        font.resize(font.len().div_ceil(4) * 4, 0);
+    }
+    font
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An empty glyph, a triangle whose bounding box is left out and a composite of the triangle
+    fn transformed_glyf() -> Vec<u8> {
+        let streams: [&[u8]; 7] = [
+            &[0, 0, 0, 1, 0xff, 0xff],
+            &[3],
+            &[127, 11, 0x80 | 126],
+            &[0, 10, 0, 20, 100, 0, 50, 0, 200, 0, 2],
+            &[0x01, 0x01, 0, 1, 0, 0, 0, 0],
+            &[0x20, 0, 0, 0, 0, 10, 0, 20, 0, 110, 0, 220],
+            &[0xb0, 0x01],
+        ];
+        let mut table = vec![0, 0, 0, 0, 0, 3, 0, 0];
+        streams.iter().for_each(|stream| table.extend_from_slice(&(stream.len() as u32).to_be_bytes()));
+        streams.iter().for_each(|stream| table.extend_from_slice(stream));
+        table
+    }
+
+    #[test]
+    fn reads_variable_length_numbers() {
+        assert_eq!(base128(&[0x3f], &mut 0), Some(63));
+        assert_eq!(base128(&[0x81, 0x00], &mut 0), Some(128));
+        assert_eq!(base128(&[0x81], &mut 0), None);
+        assert_eq!(base128(&[0x8f, 0xff, 0xff, 0xff, 0x7f], &mut 0), Some(u32::MAX));
+        assert_eq!(base128(&[0x90, 0x80, 0x80, 0x80, 0x00], &mut 0), None);
+
+        let values = [12, 253, 0x01, 0x2c, 255, 7, 254, 1];
+        let mut stream = Stream::new(&values);
+        assert_eq!((stream.u16_255(), stream.u16_255(), stream.u16_255(), stream.u16_255()), (Some(12), Some(300), Some(260), Some(507)));
+        assert_eq!(Stream::new(&[253, 1]).u16_255(), None);
+    }
+
+    #[test]
+    fn decodes_point_triplets() {
+        assert_eq!(triplet(1, &mut Stream::new(&[5])), Some((0, 5)));
+        assert_eq!(triplet(10, &mut Stream::new(&[5])), Some((-5, 0)));
+        assert_eq!(triplet(23, &mut Stream::new(&[0x12])), Some((2, 3)));
+        assert_eq!(triplet(127, &mut Stream::new(&[1, 0, 0xff, 0xff])), Some((256, 65535)));
+        assert_eq!(triplet(127, &mut Stream::new(&[1, 0])), None);
+    }
+
+    #[test]
+    fn rebuilds_transformed_glyphs() {
+        let glyphs = reconstruct_glyf(&transformed_glyf()).unwrap();
+        let triangle = [
+            &[0, 1, 0, 10, 0, 20, 0, 110, 0, 220, 0, 2, 0, 0][..],
+            &[0x37, 0x33, 0x26],
+            &[10, 100, 50],
+            &[20, 200],
+            &[0, 0],
+        ].concat();
+        let composite = [&[0xff, 0xff, 0, 10, 0, 20, 0, 110, 0, 220][..], &[0x01, 0x01, 0, 1, 0, 0, 0, 0], &[0, 2, 0xb0, 0x01], &[0, 0]].concat();
+        assert_eq!(glyphs.glyf, [triangle, composite].concat());
+        assert_eq!(glyphs.loca, [0, 0, 0, 0, 0, 12, 0, 24]);
+        assert_eq!(glyphs.x_mins, [0, 10, 10]);
+    }
+
+    #[test]
+    fn truncated_glyphs_are_not_rebuilt() {
+        let table = transformed_glyf();
+        for len in 0..table.len() {
+            assert!(reconstruct_glyf(&table[..len]).is_none());
+        }
+        // A composite has to have its bounding box stored
+        let mut table = transformed_glyf();
+        let bitmap = table.len() - 2 - 12;
+        table[bitmap] = 0;
+        assert!(reconstruct_glyf(&table).is_none());
+    }
+
+    #[test]
+    fn rebuilds_left_out_side_bearings() {
+        let hmtx = reconstruct_hmtx(&[3, 0x01, 0xf4, 0x02, 0x58], &[0, 10, 12], 2).unwrap();
+        assert_eq!(hmtx, [0x01, 0xf4, 0, 0, 0x02, 0x58, 0, 10, 0, 12]);
+        let hmtx = reconstruct_hmtx(&[0, 0x01, 0xf4, 0xff, 0xff, 0, 5], &[0, 10], 1).unwrap();
+        assert_eq!(hmtx, [0x01, 0xf4, 0xff, 0xff, 0, 5]);
+        assert!(reconstruct_hmtx(&[0, 0x01, 0xf4], &[0, 10], 1).is_none());
+        assert!(reconstruct_hmtx(&[3], &[0], 2).is_none());
+    }
+
+    #[test]
+    fn writes_the_table_directory() {
+        let table = [0u8; 4];
+        let mut tables = [*b"e   ", *b"d   ", *b"c   ", *b"b   ", *b"a   "].map(|tag| (tag, &table[..]));
+        let font = sfnt(b"\0\x01\0\0", &mut tables);
+        assert_eq!(font[4..12], [0, 5, 0, 64, 0, 2, 0, 16]);
+        assert_eq!(&font[12..16], b"a   ");
+        assert_eq!(font.len(), 12 + 5 * 16 + 5 * 4);
+        assert_eq!(sfnt(b"OTTO", &mut [])[4..12], [0; 8]);
+    }
+
+    #[test]
+    fn broken_woff2_headers_do_not_panic() {
+        let mut data = [&b"wOF2\0\x01\0\0"[..], &[0; 4], &[0, 2], &[0; 6], &[0, 0, 0, 0xff], &[0; 24]].concat();
+        data.extend_from_slice(&[0x0a, 0x81, 0x00, 0x84, 0x00, 0x3f, b't', b'e', b's', b't', 0x10]);
+        data.extend_from_slice(&[0xff; 16]);
+        for len in 0..data.len() {
+            assert!(woff2(&data[..len]).is_none());
+        }
+    }
+}
@@ -1,8 +1,10 @@
-use std::time::SystemTime;
+use std::io::{Read, Seek, SeekFrom};
+use std::time::{Duration, SystemTime};
 
-use chrono::{DateTime, Local, Duration};
+use chrono::{DateTime, Datelike, Local};
 
 use crate::components::fileitem::FileType;
+use crate::highlight;
 
 pub fn readable_size(size: u64) -> String {
     let mut size = size as f64;
@@ -37,12 +39,12 @@ pub fn readable_time(time: Option<SystemTime>) -> String {
     }
 
     // If the date is today
-    if datetime.date_naive() == now.date_naive() {
+    if days_ago(datetime, now) == 0 {
         return format!("Today at {}", datetime.format("%I:%M %p")); // 12-hour format with AM/PM
     }
 
     // If the date is yesterday
-    if datetime.date_naive() == now.date_naive() - Duration::days(1) {
+    if days_ago(datetime, now) == 1 {
         return format!("Yesterday at {}", datetime.format("%I:%M %p"));
     }
 
@@ -50,6 +52,49 @@ pub fn readable_time(time: Option<SystemTime>) -> String {
     datetime.format("%m/%d/%Y %I:%M %p").to_string()
 }
 
+// "3:07" or "1:02:45" for longer durations
+pub fn readable_duration(duration: Option<Duration>) -> String {
+    let Some(duration) = duration else { return "-".to_owned() };
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+// Number of calendar days between `datetime` and `now`, 0 being today
+fn days_ago(datetime: DateTime<Local>, now: DateTime<Local>) -> i64 {
+    (now.date_naive() - datetime.date_naive()).num_days()
+}
+
+// Coarse, human readable bucket for a date, ordered from newest (0) to oldest
+pub fn date_bucket(time: Option<SystemTime>) -> (u8, String) {
+    if time.is_none() {
+        return (u8::MAX, "Unknown".to_string());
+    }
+
+    let datetime: DateTime<Local> = DateTime::from(time.unwrap());
+    let now: DateTime<Local> = Local::now();
+    let days = days_ago(datetime, now);
+    let weekday = now.weekday().num_days_from_monday() as i64;
+    let months = (now.year() - datetime.year()) * 12 + now.month() as i32 - datetime.month() as i32;
+
+    let (order, label) = match days {
+        d if d < 0 => (0, "In the future"),
+        0 => (1, "Today"),
+        1 => (2, "Yesterday"),
+        d if d <= weekday => (3, "Earlier this week"),
+        d if d <= weekday + 7 => (4, "Last week"),
+        _ if months == 0 => (5, "Earlier this month"),
+        _ if months == 1 => (6, "Last month"),
+        _ if now.year() == datetime.year() => (7, "Earlier this year"),
+        _ if now.year() - datetime.year() == 1 => (8, "Last year"),
+        _ => (9, "A long time ago"),
+    };
+    (order, label.to_string())
+}
+
 pub fn image_from_type(file_type: FileType) -> String {
     match file_type {
         FileType::Directory => "resources/folder.ico",
@@ -75,7 +120,76 @@ pub fn file_type_from_extension(ext: &str) -> FileType {
         ext if audio_extensions.contains(&ext) => FileType::Audio,
         ext if document_extensions.contains(&ext) => FileType::Document,
         ext if misc_extensions.contains(&ext) => FileType::File,
+        ext if highlight::is_source(ext) => FileType::File,
         _ => FileType::Unknown,
     }
 }
 
+pub fn mime_from_extension(ext: &str) -> &'static str {
+    match ext {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "bmp" => "image/bmp",
+        "tiff" => "image/tiff",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/vnd.microsoft.icon",
+        "mp4" => "video/mp4",
+        "avi" => "video/x-msvideo",
+        "mkv" => "video/x-matroska",
+        "mov" => "video/quicktime",
+        "flv" => "video/x-flv",
+        "wmv" => "video/x-ms-wmv",
+        "webm" => "video/webm",
+        "mpeg" => "video/mpeg",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "aac" => "audio/aac",
+        "flac" => "audio/flac",
+        "ogg" => "audio/ogg",
+        "m4a" => "audio/mp4",
+        "pdf" => "application/pdf",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "odt" => "application/vnd.oasis.opendocument.text",
+        "rtf" => "application/rtf",
+        "epub" => "application/epub+zip",
+        "html" | "htm" => "text/html",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "zip" => "application/zip",
+        "tar" => "application/x-tar",
+        "gz" => "application/gzip",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "woff2" => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+// Formats a unix mode the way `ls -l` does, e.g. "drwxr-xr-x"
+pub fn readable_permissions(mode: u32, is_dir: bool) -> String {
+    let mut perms = String::with_capacity(10);
+    perms.push(if is_dir { 'd' } else { '-' });
+    for shift in [6, 3, 0] {
+        let bits = (mode >> shift) & 0o7;
+        perms.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        perms.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        perms.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    perms
+}
+
+// Up to `len` bytes at `offset`, fewer at the end of the file. The header parsers read
+// what they need with this instead of mapping files that might shrink under them
+pub fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, len: u64) -> Option<Vec<u8>> {
+    reader.seek(SeekFrom::Start(offset)).ok()?;
+    let mut bytes = vec![];
+    reader.take(len).read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
@@ -0,0 +1,345 @@
+use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::mp4;
+use crate::utils::{read_at, readable_duration};
+
+// How much of a Matroska file is read for its headers, they come before the media data
+const MATROSKA_HEADER_SIZE: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Default)]
+pub struct AudioTrack {
+    pub codec: Option<String>,
+    pub channels: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub language: Option<String>,
+}
+
+// What the preview and the list columns show of a video, read from the container
+#[derive(Debug, Clone, Default)]
+pub struct VideoInfo {
+    pub duration: Option<Duration>,
+    // Width and height in pixels
+    pub resolution: Option<(u32, u32)>,
+    pub frame_rate: Option<f64>,
+    pub codec: Option<String>,
+    pub audio: Vec<AudioTrack>,
+}
+
+impl VideoInfo {
+    // Label and value of every known field, in the order they're shown
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = [
+            ("Duration", self.duration.map(|d| readable_duration(Some(d)))),
+            ("Resolution", self.resolution.map(|(w, h)| format!("{}×{}", w, h))),
+            ("Frame Rate", self.frame_rate.map(|fps| format!("{:.3} fps", fps).replace(".000", ""))),
+            ("Video Codec", self.codec.clone()),
+        ]
+            .into_iter()
+            .filter_map(|(label, value)| value.map(|v| (label, v)))
+            .collect::<Vec<_>>();
+
+        for track in &self.audio {
+            let parts = [
+                track.codec.clone(),
+                track.channels.map(|c| format!("{} ch", c)),
+                track.sample_rate.map(|r| format!("{} kHz", r as f64 / 1000.0)),
+                track.language.clone(),
+            ];
+            let description = parts.into_iter().flatten().collect::<Vec<_>>().join(", ");
+            fields.push(("Audio", if description.is_empty() { "Unknown".to_owned() } else { description }));
+        }
+        fields
+    }
+}
+
+// Reads the container header by the file's extension, the streams themselves aren't read
+pub fn read(path: &Path) -> Option<VideoInfo> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    let mut file = File::open(path).ok()?;
+    match extension.as_str() {
+        "mp4" | "m4v" | "mov" => quicktime(&mp4::read_box(&mut file, b"moov")?),
+        "mkv" | "webm" => matroska(&read_at(&mut file, 0, MATROSKA_HEADER_SIZE)?),
+        _ => None,
+    }
+}
+
+fn quicktime(moov: &[u8]) -> Option<VideoInfo> {
+    let mut info = VideoInfo {
+        duration: mp4::child(moov, b"mvhd").and_then(mp4::timing).and_then(|(scale, duration)| seconds(duration, scale)),
+        ..VideoInfo::default()
+    };
+
+    for (kind, trak) in mp4::atoms(moov) {
+        if &kind != b"trak" {
+            continue;
+        }
+        let Some(mdia) = mp4::child(trak, b"mdia") else { continue };
+        // Handler type after version, flags and a predefined field
+        let handler = mp4::child(mdia, b"hdlr").and_then(|hdlr| hdlr.get(8..12));
+        let timing = mp4::child(mdia, b"mdhd").and_then(mp4::timing);
+        let stbl = mp4::find(mdia, &[b"minf", b"stbl"]);
+        // The first sample description names the codec
+        let entry = stbl
+            .and_then(|stbl| mp4::child(stbl, b"stsd"))
+            .and_then(|stsd| stsd.get(8..))
+            .and_then(|entries| mp4::atoms(entries).next());
+
+        match handler {
+            Some(b"vide") if info.codec.is_none() => {
+                if let Some((codec, entry)) = entry {
+                    info.codec = Some(codec_name(&String::from_utf8_lossy(&codec)));
+                    let width = mp4::u16_at(entry, 24).map(u32::from);
+                    let height = mp4::u16_at(entry, 26).map(u32::from);
+                    info.resolution = width.zip(height).filter(|(w, h)| *w > 0 && *h > 0);
+                }
+                // Frames over the track's own duration, the mean rate for variable frame rates
+                let frames = stbl.and_then(|stbl| mp4::child(stbl, b"stts")).map(|stts| {
+                    let count = mp4::u32_at(stts, 4).unwrap_or(0) as usize;
+                    (0..count).map_while(|i| mp4::u32_at(stts, 8 + i * 8)).map(u64::from).sum::<u64>()
+                });
+                if let (Some(frames), Some((scale, duration))) = (frames, timing) {
+                    info.frame_rate = seconds(duration, scale).map(|d| frames as f64 / d.as_secs_f64());
+                }
+            }
+            Some(b"soun") => {
+                let language = mp4::child(mdia, b"mdhd").and_then(mdhd_language);
+                info.audio.push(AudioTrack {
+                    codec: entry.map(|(codec, _)| codec_name(&String::from_utf8_lossy(&codec))),
+                    channels: entry.and_then(|(_, entry)| mp4::u16_at(entry, 16)).map(u32::from),
+                    sample_rate: timing.map(|(scale, _)| scale).filter(|r| *r > 0),
+                    language,
+                });
+            }
+            _ => {}
+        }
+    }
+    Some(info)
+}
+
+// Three letters packed into 5 bits each, "und" means not set
+fn mdhd_language(mdhd: &[u8]) -> Option<String> {
+    let (version, body) = mp4::full_box(mdhd)?;
+    let packed = mp4::u16_at(body, if version == 1 { 28 } else { 16 })?;
+    let language = [10, 5, 0].iter().map(|shift| (((packed >> shift) & 0x1f) as u8 + 0x60) as char).collect::<String>();
+    (language != "und" && language.chars().all(|c| c.is_ascii_lowercase())).then_some(language)
+}
+
+fn seconds(duration: u64, scale: u32) -> Option<Duration> {
+    (scale > 0 && duration > 0).then(|| Duration::try_from_secs_f64(duration as f64 / scale as f64).ok()).flatten()
+}
+
+// Friendly names for the codec identifiers of both containers
+fn codec_name(id: &str) -> String {
+    let name = match id.trim_end_matches('\0') {
+        "avc1" | "avc3" | "V_MPEG4/ISO/AVC" => "H.264",
+        "hvc1" | "hev1" | "V_MPEGH/ISO/HEVC" => "H.265",
+        "vp08" | "V_VP8" => "VP8",
+        "vp09" | "V_VP9" => "VP9",
+        "av01" | "V_AV1" => "AV1",
+        "mp4v" | "V_MPEG4/ISO/SP" | "V_MPEG4/ISO/ASP" => "MPEG-4 Visual",
+        "apcn" | "apch" | "apcs" | "apco" | "ap4h" => "ProRes",
+        "mp4a" | "A_AAC" => "AAC",
+        "ac-3" | "A_AC3" => "AC-3",
+        "ec-3" | "A_EAC3" => "E-AC-3",
+        "Opus" | "A_OPUS" => "Opus",
+        "fLaC" | "A_FLAC" => "FLAC",
+        "A_VORBIS" => "Vorbis",
+        ".mp3" | "A_MPEG/L3" => "MP3",
+        "lpcm" | "sowt" | "twos" | "A_PCM/INT/LIT" => "PCM",
+        id if id.starts_with("A_DTS") => "DTS",
+        id => id,
+    };
+    name.to_owned()
+}
+
+// Matroska element ids, with their length marker
+const SEGMENT: u32 = 0x18538067;
+const INFO: u32 = 0x1549A966;
+const TIMECODE_SCALE: u32 = 0x2AD7B1;
+const DURATION: u32 = 0x4489;
+const TRACKS: u32 = 0x1654AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_TYPE: u32 = 0x83;
+const CODEC_ID: u32 = 0x86;
+const DEFAULT_DURATION: u32 = 0x23E383;
+const LANGUAGE: u32 = 0x22B59C;
+const VIDEO: u32 = 0xE0;
+const PIXEL_WIDTH: u32 = 0xB0;
+const PIXEL_HEIGHT: u32 = 0xBA;
+const AUDIO: u32 = 0xE1;
+const SAMPLING_FREQUENCY: u32 = 0xB5;
+const CHANNELS: u32 = 0x9F;
+const CLUSTER: u32 = 0x1F43B675;
+
+fn matroska(data: &[u8]) -> Option<VideoInfo> {
+    if data.get(0..4)? != [0x1A, 0x45, 0xDF, 0xA3] {
+        return None;
+    }
+    let segment = elements(data).find(|(id, _)| *id == SEGMENT)?.1;
+    let mut info = VideoInfo::default();
+
+    // Info and Tracks come before the first cluster in practice, the media data isn't walked
+    for (id, body) in elements(segment).take_while(|(id, _)| *id != CLUSTER) {
+        match id {
+            INFO => {
+                let scale = element(body, TIMECODE_SCALE).map(uint).unwrap_or(1_000_000);
+                let duration = element(body, DURATION).and_then(float);
+                info.duration = duration.and_then(|d| Duration::try_from_secs_f64(d * scale as f64 / 1e9).ok());
+            }
+            TRACKS => {
+                for (_, track) in elements(body).filter(|(id, _)| *id == TRACK_ENTRY) {
+                    let codec = element(track, CODEC_ID).map(|id| codec_name(&String::from_utf8_lossy(id)));
+                    match element(track, TRACK_TYPE).map(uint) {
+                        Some(1) if info.codec.is_none() => {
+                            info.codec = codec;
+                            let video = element(track, VIDEO).unwrap_or_default();
+                            let width = element(video, PIXEL_WIDTH).map(uint);
+                            let height = element(video, PIXEL_HEIGHT).map(uint);
+                            info.resolution = width.zip(height).map(|(w, h)| (w as u32, h as u32));
+                            // Nanoseconds per frame
+                            info.frame_rate = element(track, DEFAULT_DURATION).map(uint).filter(|d| *d > 0).map(|d| 1e9 / d as f64);
+                        }
+                        Some(2) => {
+                            let audio = element(track, AUDIO).unwrap_or_default();
+                            info.audio.push(AudioTrack {
+                                codec,
+                                channels: element(audio, CHANNELS).map(|c| uint(c) as u32),
+                                // The spec's default when it's missing
+                                sample_rate: Some(element(audio, SAMPLING_FREQUENCY).and_then(float).unwrap_or(8000.0) as u32),
+                                language: element(track, LANGUAGE)
+                                    .map(|l| String::from_utf8_lossy(l).trim_end_matches('\0').to_owned())
+                                    .filter(|l| !l.is_empty() && l != "und"),
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(info)
+}
+
+// Id and body of every EBML element in `data`, elements of unknown size run to the end
+fn elements(data: &[u8]) -> impl Iterator<Item = (u32, &[u8])> {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        let (Some(id), id_len) = vint(rest, true)? else { return None };
+        let (size, size_len) = vint(rest.get(id_len..)?, false)?;
+        let start = id_len + size_len;
+        let end = size.map(|size| start.saturating_add(size as usize)).unwrap_or(rest.len()).min(rest.len());
+        let body = rest.get(start..end)?;
+        rest = &rest[end..];
+        Some((id as u32, body))
+    })
+}
+
+fn element(data: &[u8], id: u32) -> Option<&[u8]> {
+    elements(data).find(|(i, _)| *i == id).map(|(_, body)| body)
+}
+
+// Variable length integer, ids keep their length marker, sizes of all ones are unknown (None)
+fn vint(data: &[u8], keep_marker: bool) -> Option<(Option<u64>, usize)> {
+    let first = *data.first()?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 || data.len() < len {
+        return None;
+    }
+    let marker = if keep_marker { first } else { first & (0xff >> len) };
+    let value = data[1..len].iter().fold(marker as u64, |value, b| (value << 8) | *b as u64);
+    let unknown = !keep_marker && value == (1u64 << (7 * len)) - 1;
+    Some((if unknown { None } else { Some(value) }, len))
+}
+
+fn uint(body: &[u8]) -> u64 {
+    body.iter().take(8).fold(0, |value, b| (value << 8) | *b as u64)
+}
+
+fn float(body: &[u8]) -> Option<f64> {
+    match body.len() {
+        4 => Some(f32::from_be_bytes(body.try_into().ok()?) as f64),
+        8 => Some(f64::from_be_bytes(body.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ids are written with their length marker, sizes take a single byte
+    fn ebml(id: u32, body: &[u8]) -> Vec<u8> {
+        let id = id.to_be_bytes();
+        let start = id.iter().position(|b| *b != 0).unwrap_or(3);
+        [&id[start..], &[0x80 | body.len() as u8], body].concat()
+    }
+
+    fn mkv(info: &[u8], tracks: &[u8]) -> Vec<u8> {
+        let segment = [ebml(INFO, info), ebml(TRACKS, tracks)].concat();
+        // The segment's size is unknown, like in files written while streaming
+        [&[0x1A, 0x45, 0xDF, 0xA3, 0x80][..], &SEGMENT.to_be_bytes(), &[0xFF], &segment].concat()
+    }
+
+    fn boxed(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        [&(body.len() as u32 + 8).to_be_bytes()[..], kind, body].concat()
+    }
+
+    fn mvhd(timescale: u32, duration: u64) -> Vec<u8> {
+        boxed(b"mvhd", &[&[1, 0, 0, 0][..], &[0; 16], &timescale.to_be_bytes(), &duration.to_be_bytes()].concat())
+    }
+
+    #[test]
+    fn reads_matroska_tracks() {
+        let video = ebml(VIDEO, &[ebml(PIXEL_WIDTH, &[0x07, 0x80]), ebml(PIXEL_HEIGHT, &[0x04, 0x38])].concat());
+        let track = [ebml(TRACK_TYPE, &[1]), ebml(CODEC_ID, b"V_VP9"), video].concat();
+        let info = ebml(DURATION, &2500.0f64.to_be_bytes());
+        let info = matroska(&mkv(&info, &ebml(TRACK_ENTRY, &track))).unwrap();
+        assert_eq!(info.duration, Some(Duration::from_millis(2500)));
+        assert_eq!(info.resolution, Some((1920, 1080)));
+        assert_eq!(info.codec.as_deref(), Some("VP9"));
+    }
+
+    #[test]
+    fn broken_matroska_headers_do_not_panic() {
+        assert!(matroska(&[0x1A, 0x45]).is_none());
+        assert!(matroska(b"not a video").is_none());
+        // A header without a segment, and one cut off in the middle of an element
+        assert!(matroska(&[0x1A, 0x45, 0xDF, 0xA3, 0x80]).is_none());
+        let file = mkv(&ebml(DURATION, &2500.0f64.to_be_bytes()), &[]);
+        for len in 0..file.len() {
+            matroska(&file[..len]);
+        }
+        // A size with more than 8 bytes
+        assert!(vint(&[0, 1], false).is_none());
+    }
+
+    #[test]
+    fn matroska_durations_that_do_not_fit_are_unknown() {
+        for duration in [1e300, -1.0, f64::NAN] {
+            let info = matroska(&mkv(&ebml(DURATION, &duration.to_be_bytes()), &[])).unwrap();
+            assert_eq!(info.duration, None);
+        }
+    }
+
+    #[test]
+    fn quicktime_durations_that_do_not_fit_are_unknown() {
+        assert_eq!(quicktime(&mvhd(1, u64::MAX)).unwrap().duration, None);
+        assert_eq!(quicktime(&mvhd(1, u64::MAX - 1)).unwrap().duration, None);
+        assert_eq!(quicktime(&mvhd(0, 10)).unwrap().duration, None);
+        assert_eq!(quicktime(&mvhd(10, 25)).unwrap().duration, Some(Duration::from_millis(2500)));
+    }
+
+    #[test]
+    fn truncated_tracks_are_skipped() {
+        let hdlr = boxed(b"hdlr", &[&[0; 8][..], b"vide"].concat());
+        let trak = boxed(b"trak", &boxed(b"mdia", &hdlr));
+        let moov = [mvhd(10, 25), trak].concat();
+        for len in 0..moov.len() {
+            quicktime(&moov[..len]);
+        }
+        assert_eq!(quicktime(&moov).unwrap().codec, None);
+    }
+}
@@ -1,8 +1,14 @@
+pub mod dataview;
 pub mod explorer;
+pub mod foldertree;
+pub mod markdown;
+pub mod miller;
+pub mod preview;
+pub mod tabs;
 
-pub use explorer::{Explorer};
+pub use tabs::{Tabs};
 
 pub enum View {
     Loading,
-    Explorer(Explorer),
+    Tabs(Tabs),
 }
\ No newline at end of file
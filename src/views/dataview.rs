@@ -0,0 +1,143 @@
+use std::collections::HashSet;
+
+use iced::widget::{button, column, container, scrollable, text, Column, Row};
+use iced::{Element, Font, Padding, Theme};
+
+use crate::structured::{Node, ParseError, ScalarKind};
+use crate::tabular::Table;
+use crate::views::explorer::Message;
+
+const CELL_WIDTH: f32 = 120.0;
+
+// Children shown per object or array, the rest is summed up
+const MAX_CHILDREN: usize = 500;
+
+// Nodes this deep are expanded until toggled
+const EXPANDED_DEPTH: usize = 2;
+
+// Lines of source shown around a parse error
+const ERROR_CONTEXT: usize = 3;
+
+pub fn table_view(table: &Table) -> Element<Message> {
+    let delimiter = match table.delimiter {
+        '\t' => "Tab".to_owned(),
+        c => format!("\"{}\"", c),
+    };
+    let header = table.header.as_ref().map(|cells| {
+        table_row(cells, |theme: &Theme| text::Style { color: Some(theme.palette().primary) })
+    });
+    let rows = table.rows.iter().map(|cells| table_row(cells, |_: &Theme| text::Style::default()));
+
+    column![
+        text(format!("{} rows, separated by {}", table.rows.len(), delimiter)).size(12),
+        scrollable(Column::new().push_maybe(header).extend(rows).spacing(2))
+            .direction(scrollable::Direction::Horizontal(scrollable::Scrollbar::default())),
+    ]
+        .spacing(5)
+        .into()
+}
+
+fn table_row(cells: &[String], style: fn(&Theme) -> text::Style) -> Element<Message> {
+    Row::with_children(cells.iter().map(|cell| {
+        container(text(cell.clone()).size(12).style(style))
+            .width(CELL_WIDTH)
+            .clip(true)
+            .into()
+    }))
+        .spacing(8)
+        .into()
+}
+
+// `toggled` holds the paths of nodes that were expanded or collapsed by hand
+pub fn tree_view<'a>(root: &'a Node, toggled: &'a HashSet<String>) -> Element<'a, Message> {
+    let mut rows = vec![];
+    node_rows("$".to_owned(), "$".to_owned(), root, 0, toggled, &mut rows);
+    Column::with_children(rows).spacing(2).into()
+}
+
+fn node_rows<'a>(key: String, path: String, node: &'a Node, depth: usize, toggled: &HashSet<String>, rows: &mut Vec<Element<'a, Message>>) {
+    let indent = Padding::new(0.0).left(depth as f32 * 12.0);
+
+    if let Node::Scalar(kind, value) = node {
+        let kind = *kind;
+        let value = if kind == ScalarKind::String { format!("\"{}\"", value) } else { value.clone() };
+        rows.push(
+            Row::new()
+                .push(text(format!("{}:", key)).size(12).font(Font::MONOSPACE))
+                .push(text(value).size(12).font(Font::MONOSPACE).style(move |theme: &Theme| text::Style {
+                    color: Some(scalar_color(kind, theme)),
+                }))
+                .spacing(5)
+                .padding(indent)
+                .into()
+        );
+        return;
+    }
+
+    let expanded = (depth < EXPANDED_DEPTH) != toggled.contains(&path);
+    let (open, close) = if matches!(node, Node::Array(_)) { ("[", "]") } else { ("{", "}") };
+    rows.push(
+        button(
+            text(format!("{} {} {}{}{}", if expanded { "▾" } else { "▸" }, key, open, node.len(), close))
+                .size(12)
+                .font(Font::MONOSPACE)
+                .shaping(text::Shaping::Advanced)
+        )
+            .on_press(Message::TreeNodeToggled(path.clone()))
+            .padding(indent)
+            .style(button::text)
+            .into()
+    );
+    if !expanded {
+        return;
+    }
+
+    let children = node.children();
+    let hidden = children.len().saturating_sub(MAX_CHILDREN);
+    for (child_key, child) in children.into_iter().take(MAX_CHILDREN) {
+        let child_path = format!("{}/{}", path, child_key);
+        node_rows(child_key, child_path, child, depth + 1, toggled, rows);
+    }
+    if hidden > 0 {
+        rows.push(
+            text(format!("… {} more", hidden))
+                .size(12)
+                .into()
+        );
+    }
+}
+
+// The message with the lines around the error, the failing one marked
+pub fn error_view<'a>(source: &str, error: &ParseError) -> Element<'a, Message> {
+    let mut view = Column::new()
+        .push(text(error.message.clone()).size(12).style(|theme: &Theme| text::Style {
+            color: Some(theme.palette().danger),
+        }))
+        .spacing(2);
+
+    if let Some(line) = error.line {
+        let first = line.saturating_sub(ERROR_CONTEXT + 1);
+        for (i, content) in source.lines().enumerate().skip(first).take(ERROR_CONTEXT * 2 + 1) {
+            let failed = i + 1 == line;
+            view = view.push(
+                text(format!("{:>5} {}", i + 1, content))
+                    .size(12)
+                    .font(Font::MONOSPACE)
+                    .style(move |theme: &Theme| text::Style {
+                        color: failed.then(|| theme.palette().danger),
+                    })
+            );
+        }
+    }
+    view.into()
+}
+
+fn scalar_color(kind: ScalarKind, theme: &Theme) -> iced::Color {
+    let palette = theme.extended_palette();
+    match kind {
+        ScalarKind::Null => palette.background.strong.color,
+        ScalarKind::Bool => palette.primary.base.color,
+        ScalarKind::Number => palette.danger.base.color,
+        ScalarKind::String => palette.success.base.color,
+    }
+}
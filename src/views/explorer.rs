@@ -1,22 +1,33 @@
+use std::collections::{HashMap, HashSet};
 use std::path::{PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
 
 use iced::advanced::{mouse, widget::operation};
 use iced::widget::button::Style;
+use iced::widget::image::Handle;
 use iced::widget::scrollable::{Id, RelativeOffset};
-use iced::widget::{button, column, container, mouse_area, row, scrollable, text, text_input, Column, Space};
-use iced::{event, window, Color, Element, Event, Length, Padding, Pixels, Size, Subscription, Task};
+use iced::widget::{button, checkbox, column, container, mouse_area, pick_list, row, scrollable, slider, text, text_input, Column, Image, Row, Space};
+use iced::{window, Color, Element, Event, Font, Length, Padding, Pixels, Point, Size, Task};
 use iced_runtime::{Action, task};
 
-use crate::components::fileitem::{FileItem, FileData};
+use crate::columns::ColumnKind;
+use crate::components::fileitem::{FileItem, FileData, FileType};
+use crate::components::filetile::FileTile;
+use crate::grouping::{group_files, GroupBy};
+use crate::loader::{LoadEvent, Listing, Loader};
 use crate::platform::Platform;
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum SortBy {
-    Name,
-    Size,
-    CreatedAt,
-    LastModified,
-}
+use crate::preview::Preview;
+use crate::search;
+use crate::session::PaneState;
+use crate::settings::{Settings, SortScope, ViewMode};
+use crate::sorting::{sort_files, Sort, SortBy};
+use crate::thumbnails;
+use crate::utils::{image_from_type, readable_size};
+use crate::views::foldertree::FolderTree;
+use crate::views::miller::{self, MillerColumn};
+use crate::views::preview::{HexMessage, PreviewPane, PREVIEW_WIDTH};
 
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -24,25 +35,82 @@ pub enum Message {
     OpenFile(FileData),
     History(bool),
 
-    LoadTree(Vec<FileData>),
+    LoadTree(LoadEvent),
     SortTree(SortBy),
+    SecondarySortChanged(SortBy),
+    FoldersFirstToggled(bool),
+    GroupByChanged(GroupBy),
+    ToggleGroup(String),
+    ToggleColumnChooser,
+    ColumnToggled(ColumnKind, bool),
+    ColumnResizeStarted(usize),
+    ColumnDragStarted(usize),
+    ColumnHovered(usize),
+    ViewModeChanged(ViewMode),
+    ZoomChanged(f32),
+    ZoomReleased,
+    ThumbnailLoaded(PathBuf, Option<Handle>),
+    TogglePreview,
+    PreviewLoaded(u64, Option<Preview>),
+    PreviewLoadMore,
+    FontLoaded(PathBuf, Option<SystemTime>, Option<Font>),
+    Hex(HexMessage),
+    MarkdownRawToggled,
+    OpenLink(String),
+    TreeNodeToggled(String),
+    OpenInNewTab(FileData),
+    OpenInNewWindow(PathBuf),
+    ToggleBookmark,
+    Scrolled(RelativeOffset),
+    FolderTreeToggled(FileData),
+    FolderTreeLoaded(PathBuf, LoadEvent),
+    ToggleExpanded(FileData),
+    ExpandedLoaded(PathBuf, LoadEvent),
+    MillerSelect(usize, FileData),
+    MillerLoaded(usize, LoadEvent),
+    SortScopeToggled(bool),
+    RestoreSessionToggled(bool),
+    StartDirChosen,
     EventOccurred(Event),
     WindowResized(Size),
 
     ClickedOn(container::Id),
 
     AddressbarChanged(String),
+    SearchChanged(String),
     DirChanged,
 }
 
 pub struct Explorer {
     current_path: PathBuf,
     tree: Option<Vec<FileData>>,
+    loader: Loader,
+
+    sort: Sort,
+    settings: Settings,
+    // Set when the settings changed so the other tabs can pick them up
+    settings_changed: bool,
+    scroll_offset: RelativeOffset,
+
+    highlighted_file: Option<PathBuf>,
+    collapsed_groups: HashSet<String>,
+    column_chooser_open: bool,
+    cursor: Point,
+    resizing_column: Option<usize>,
+    dragging_column: Option<usize>,
+    hovered_column: Option<usize>,
 
-    sortby: SortBy,
-    sort_ascending: bool,
+    thumbnails: HashMap<PathBuf, Handle>,
+    pending_thumbnails: HashSet<PathBuf>,
+    // Set when the directory is left so its queued thumbnails are skipped
+    thumbnails_cancelled: Arc<AtomicBool>,
 
-    highlighted_file: Option<String>,
+    folder_tree: FolderTree,
+    preview: PreviewPane,
+    // Directories expanded inline in the tree view
+    expanded: HashMap<PathBuf, Listing>,
+    // Columns opened to the right of the current directory in the column view
+    miller: Vec<MillerColumn>,
 
     width: Option<f32>,
     height: Option<f32>,
@@ -52,50 +120,244 @@ pub struct Explorer {
 
     addressbar_focused: bool,
     addressbar_content: String,
-}
+    // Filters the current directory, see `search::matches`
+    search: String,
+    // Entries of the current directory the search matches, None without a search
+    matches: Option<HashSet<PathBuf>>,
 
-fn load_tree(path: PathBuf) -> Task<Message> {
-    Task::perform(
-        async {
-            std::fs::read_dir(path)
-                .ok()
-                .into_iter()
-                .flatten()
-                .filter_map(Result::ok)
-                .map(|e| { FileData::new(e.path()) })
-                .collect::<Vec<_>>()
-        },
-        Message::LoadTree
-    )
+    // Unique per explorer so two panes never scroll or focus each other
+    list_id: Id,
+    miller_id: Id,
+    addressbar_id: text_input::Id,
 }
 
 impl Explorer {
     pub fn new() -> (Self, Task<Message>) {
+        Self::at(Settings::load().start_dir())
+    }
+
+    // Reopens a pane saved by `session`, dropping folders that were deleted since
+    pub fn restore(state: PaneState) -> (Self, Task<Message>) {
+        let current = state.history.get(state.history_index).filter(|p| p.is_dir()).cloned();
+        let (mut explorer, task) = Self::at(current.clone().unwrap_or_else(|| Settings::load().start_dir()));
+        if current.is_some() {
+            explorer.history_index = state.history.iter().take(state.history_index).filter(|p| p.is_dir()).count();
+            explorer.history = state.history.into_iter().filter(|p| p.is_dir()).collect();
+            explorer.sort = state.sort;
+        }
+        (explorer, task)
+    }
+
+    pub fn session(&self) -> PaneState {
+        PaneState {
+            history: self.history.clone(),
+            history_index: self.history_index,
+            sort: self.sort.clone(),
+        }
+    }
+
+    pub fn at(path: PathBuf) -> (Self, Task<Message>) {
+        let settings = Settings::load();
+        let mut explorer = Self {
+            current_path: path.clone(),
+            tree: None,
+            loader: Loader::new(),
+            highlighted_file: None,
+            collapsed_groups: HashSet::new(),
+            column_chooser_open: false,
+            cursor: Point::ORIGIN,
+            resizing_column: None,
+            dragging_column: None,
+            hovered_column: None,
+            thumbnails: HashMap::new(),
+            pending_thumbnails: HashSet::new(),
+            thumbnails_cancelled: Arc::new(AtomicBool::new(false)),
+            folder_tree: FolderTree::new(),
+            preview: PreviewPane::new(),
+            expanded: HashMap::new(),
+            miller: vec![],
+            width: None,
+            height: None,
+            sort: settings.sort_for(&path),
+            settings,
+            settings_changed: false,
+            scroll_offset: RelativeOffset::START,
+            history: vec![path],
+            history_index: 0,
+            addressbar_focused: false,
+            addressbar_content: "".to_string(),
+            search: String::new(),
+            matches: None,
+            list_id: Id::unique(),
+            miller_id: Id::unique(),
+            addressbar_id: text_input::Id::unique(),
+        };
+        let load = explorer.load_tree();
+        let restore = explorer.folder_tree.restore(&explorer.settings.expanded_folders.clone());
         (
-            Self {
-                current_path: Platform::home_dir(),
-                tree: None,
-                highlighted_file: None,
-                width: None,
-                height: None,
-                sortby: SortBy::Name,
-                sort_ascending: true,
-                history: vec![Platform::home_dir()],
-                history_index: 0,
-                addressbar_focused: false,
-                addressbar_content: "".to_string(),
-            },
+            explorer,
             Task::batch(vec![
-                window::get_oldest().and_then(window::get_size).map(|size| {
-                    Message::WindowResized(size)
-                }),
-                load_tree(Platform::home_dir()),
+                load,
+                restore,
             ])
         )
     }
 
-    pub fn open() -> (Self, Task<Message>) {
-        Self::new()
+    // Starts streaming the current directory in, dropping whatever was still loading
+    fn load_tree(&mut self) -> Task<Message> {
+        self.sort = self.settings.sort_for(&self.current_path);
+        self.tree = Some(vec![]);
+        self.refilter();
+        self.thumbnails.clear();
+        self.pending_thumbnails.clear();
+        self.thumbnails_cancelled.store(true, Ordering::Relaxed);
+        self.thumbnails_cancelled = Arc::new(AtomicBool::new(false));
+        self.miller.clear();
+        self.expanded.clear();
+        self.preview.clear();
+        self.loader.options.count_items = self.settings.columns.contains(ColumnKind::ItemCount);
+        self.loader.options.read_taken = self.needs(&[ColumnKind::DateTaken]);
+        self.loader.options.read_tags = self.needs(&ColumnKind::TAGS);
+        self.loader.options.read_video = self.needs(&ColumnKind::VIDEO);
+        self.loader.options.read_documents = !self.search.trim().is_empty();
+        Task::batch(vec![
+            self.loader.start(self.current_path.clone()).map(Message::LoadTree),
+            self.folder_tree.reveal(&self.current_path),
+        ])
+    }
+
+    // Width left for the files next to the sidebar and the preview
+    fn list_width(&self) -> f32 {
+        let preview = if self.settings.show_preview { PREVIEW_WIDTH + 5.0 } else { 0.0 };
+        self.width.unwrap_or(200.0) - 200.0 - preview
+    }
+
+    // Photos, songs and videos are only opened for their metadata when a column of it is shown or sorted by
+    fn needs(&self, columns: &[ColumnKind]) -> bool {
+        columns.iter().any(|column| {
+            self.settings.columns.contains(*column)
+                || self.sort.by == column.sort_by()
+                || self.sort.then_by == column.sort_by()
+        })
+    }
+
+    // Whether the current listing was loaded without metadata that is needed now
+    fn misses_metadata(&self) -> bool {
+        (self.needs(&[ColumnKind::DateTaken]) && !self.loader.options.read_taken)
+            || (self.needs(&ColumnKind::TAGS) && !self.loader.options.read_tags)
+            || (self.needs(&ColumnKind::VIDEO) && !self.loader.options.read_video)
+            || (!self.search.trim().is_empty() && !self.loader.options.read_documents)
+    }
+
+    // Runs the search over the whole current directory again
+    fn refilter(&mut self) {
+        self.matches = (!self.search.trim().is_empty()).then(HashSet::new);
+        let tree = self.tree.take();
+        self.filter(tree.iter().flatten());
+        self.tree = tree;
+    }
+
+    // Checks `entries` against the search as they come in, the others keep their result
+    fn filter<'a>(&mut self, entries: impl IntoIterator<Item = &'a FileData>) {
+        let Some(matches) = self.matches.as_mut() else { return };
+        for data in entries {
+            if search::matches(data, &self.search) {
+                matches.insert(data.path.clone());
+            } else {
+                matches.remove(&data.path);
+            }
+        }
+    }
+
+    // The current directory as the search leaves it
+    fn visible_tree(&self) -> Vec<FileData> {
+        self.tree.iter()
+            .flatten()
+            .filter(|data| self.matches.as_ref().is_none_or(|matches| matches.contains(&data.path)))
+            .cloned()
+            .collect()
+    }
+
+    fn resort(&mut self) {
+        if let Some(tree) = self.tree.as_mut() {
+            sort_files(tree, &self.sort, self.settings.folders_first);
+        }
+        for listing in self.expanded.values_mut() {
+            sort_files(&mut listing.tree, &self.sort, self.settings.folders_first);
+        }
+        for column in self.miller.iter_mut() {
+            sort_files(&mut column.listing.tree, &self.sort, self.settings.folders_first);
+        }
+    }
+
+    fn save_settings(&mut self) {
+        self.settings.save();
+        self.settings_changed = true;
+    }
+
+    // Returns the settings once after every change
+    pub fn take_changed_settings(&mut self) -> Option<Settings> {
+        if std::mem::take(&mut self.settings_changed) {
+            Some(self.settings.clone())
+        } else {
+            None
+        }
+    }
+
+    // Replaces the settings with ones changed somewhere else, keeping this tab's sort
+    pub fn set_settings(&mut self, settings: Settings) {
+        self.settings = settings;
+        self.resort();
+    }
+
+    pub fn current_path(&self) -> &PathBuf {
+        &self.current_path
+    }
+
+    // The selected entry, if it's something that can be copied or moved
+    // The loaded entry of the selection, the deepest one in the column view
+    fn selected_data(&self) -> Option<FileData> {
+        if self.settings.view_mode == ViewMode::Columns {
+            if let Some(data) = self.miller.iter().rev().find_map(MillerColumn::selected_data) {
+                return Some(data.clone());
+            }
+        }
+        let path = self.highlighted_file.as_ref()?;
+        self.tree.iter().flatten()
+            .chain(self.expanded.values().flat_map(|listing| listing.tree.iter()))
+            .find(|data| &data.path == path)
+            .cloned()
+    }
+
+    pub fn selected(&self) -> Option<PathBuf> {
+        self.highlighted_file.clone().filter(|path| !self.current_path.starts_with(path))
+    }
+
+    // Reloads `dir` wherever it is shown: as the current directory, expanded inline or as a column
+    pub fn reload(&mut self, dir: &PathBuf) -> Task<Message> {
+        if &self.current_path == dir {
+            return self.load_tree();
+        }
+        let mut tasks = vec![];
+        if self.expanded.contains_key(dir) {
+            let (listing, task) = Listing::open(dir.clone());
+            self.expanded.insert(dir.clone(), listing);
+            let path = dir.clone();
+            tasks.push(task.map(move |event| Message::ExpandedLoaded(path.clone(), event)));
+        }
+        if let Some(i) = self.miller.iter().position(|column| &column.listing.path == dir) {
+            let (mut column, task) = MillerColumn::open(dir.clone());
+            column.selected = self.miller[i].selected.take();
+            self.miller[i] = column;
+            let index = i + 1;
+            tasks.push(task.map(move |event| Message::MillerLoaded(index, event)));
+        }
+        Task::batch(tasks)
+    }
+
+    // Scrolls back to where the list was when this tab was last shown
+    pub fn restore_scroll(&self) -> Task<Message> {
+        scrollable::snap_to(self.list_id.clone(), self.scroll_offset)
     }
 
     pub fn update(&mut self, message: Message) -> Task<Message> {
@@ -116,8 +378,8 @@ impl Explorer {
                     self.history_index += 1;
 
                     Task::batch(vec![
-                        load_tree(item.path),
-                        scrollable::snap_to(Id::new("explorer"), RelativeOffset { x: 0.0, y: 0.0 }),
+                        self.load_tree(),
+                        scrollable::snap_to(self.list_id.clone(), RelativeOffset { x: 0.0, y: 0.0 }),
                     ])
                 }
             }
@@ -134,42 +396,283 @@ impl Explorer {
                     }
                 }
                 Task::batch(vec![
-                    load_tree(self.current_path.clone()),
-                    scrollable::snap_to(Id::new("explorer"), RelativeOffset { x: 0.0, y: 0.0 }),
+                    self.load_tree(),
+                    scrollable::snap_to(self.list_id.clone(), RelativeOffset { x: 0.0, y: 0.0 }),
                 ])
             }
-            Message::LoadTree(mut tree) => {
-                self.sortby = SortBy::Name;
-                self.sort_ascending = true;
-                
-                tree.sort_by_key(|i| !i.is_dir);
-                self.tree = Some(tree);
+            Message::LoadTree(event) => {
+                if !self.loader.is_current(&event) {
+                    return Task::none();
+                }
+                if let LoadEvent::Entries(_, entries) | LoadEvent::Metadata(_, entries) = &event {
+                    self.filter(entries);
+                }
+
+                let tree = self.tree.get_or_insert_with(Vec::new);
+                match event {
+                    LoadEvent::Entries(_, entries) => {
+                        tree.extend(entries);
+                        sort_files(tree, &self.sort, self.settings.folders_first);
+                        return self.request_thumbnails();
+                    }
+                    LoadEvent::Metadata(_, entries) => {
+                        let mut filled = entries.into_iter()
+                            .map(|e| (e.path.clone(), e))
+                            .collect::<HashMap<_, _>>();
+                        for item in tree.iter_mut() {
+                            if let Some(data) = filled.remove(&item.path) {
+                                *item = data;
+                            }
+                        }
+                    }
+                    LoadEvent::Finished(_) => {
+                        // Sizes and dates are only known now
+                        sort_files(tree, &self.sort, self.settings.folders_first);
+                        self.loader.finish();
+                    }
+                }
                 Task::none()
             }
             Message::SortTree(by) => {
-                if self.tree.is_none() {
+                self.sort = self.sort.clicked(by);
+                self.settings.set_sort_for(&self.current_path, self.sort.clone());
+                self.save_settings();
+                if self.misses_metadata() {
+                    return self.load_tree();
+                }
+                self.resort();
+                Task::none()
+            }
+            Message::SecondarySortChanged(by) => {
+                self.sort.then_by = by;
+                self.settings.set_sort_for(&self.current_path, self.sort.clone());
+                self.save_settings();
+                if self.misses_metadata() {
+                    return self.load_tree();
+                }
+                self.resort();
+                Task::none()
+            }
+            Message::FoldersFirstToggled(folders_first) => {
+                self.settings.folders_first = folders_first;
+                self.save_settings();
+                self.resort();
+                Task::none()
+            }
+            Message::GroupByChanged(group_by) => {
+                self.settings.group_by = group_by;
+                self.save_settings();
+                self.collapsed_groups.clear();
+                Task::none()
+            }
+            Message::ToggleGroup(label) => {
+                if !self.collapsed_groups.remove(&label) {
+                    self.collapsed_groups.insert(label);
+                }
+                Task::none()
+            }
+            Message::ToggleColumnChooser => {
+                self.column_chooser_open = !self.column_chooser_open;
+                Task::none()
+            }
+            Message::ColumnToggled(column, shown) => {
+                if shown {
+                    self.settings.columns.show(column);
+                } else {
+                    self.settings.columns.hide(column);
+                }
+                self.save_settings();
+
+                // Directories are only counted when the column is shown
+                if column == ColumnKind::ItemCount && shown {
+                    return self.load_tree();
+                }
+                if shown && self.misses_metadata() {
+                    return self.load_tree();
+                }
+                Task::none()
+            }
+            Message::ColumnResizeStarted(index) => {
+                self.resizing_column = Some(index);
+                Task::none()
+            }
+            Message::ColumnDragStarted(index) => {
+                self.dragging_column = Some(index);
+                self.hovered_column = Some(index);
+                Task::none()
+            }
+            Message::ColumnHovered(index) => {
+                self.hovered_column = Some(index);
+                Task::none()
+            }
+            Message::ViewModeChanged(view_mode) => {
+                self.settings.view_mode = view_mode;
+                self.save_settings();
+                self.request_thumbnails()
+            }
+            Message::ZoomChanged(zoom) => {
+                self.settings.zoom = zoom;
+                Task::none()
+            }
+            Message::ZoomReleased => {
+                self.save_settings();
+                Task::none()
+            }
+            Message::TogglePreview => {
+                self.settings.show_preview = !self.settings.show_preview;
+                self.save_settings();
+                match self.selected_data().filter(|_| self.settings.show_preview) {
+                    Some(data) => self.preview.show(data),
+                    None => {
+                        self.preview.clear();
+                        Task::none()
+                    }
+                }
+            }
+            Message::PreviewLoaded(id, preview) => self.preview.apply(id, preview),
+            Message::FontLoaded(path, modified, font) => {
+                self.preview.font_loaded(path, modified, font);
+                Task::none()
+            }
+            Message::PreviewLoadMore => self.preview.load_more(),
+            Message::Hex(message) => self.preview.update_hex(message),
+            Message::MarkdownRawToggled => {
+                self.preview.markdown_raw = !self.preview.markdown_raw;
+                Task::none()
+            }
+            Message::SearchChanged(search) => {
+                self.search = search;
+                // Documents are read the first time a search needs them
+                if self.misses_metadata() {
+                    return self.load_tree();
+                }
+                self.refilter();
+                Task::none()
+            }
+            Message::TreeNodeToggled(path) => {
+                self.preview.toggle_node(path);
+                Task::none()
+            }
+            Message::OpenLink(link) => {
+                if let Err(e) = Platform::open(&link) {
+                    println!("Failed to open {}: {}", link, e);
+                }
+                Task::none()
+            }
+            Message::ThumbnailLoaded(path, handle) => {
+                // Thumbnails of a directory we already left are dropped
+                if !self.pending_thumbnails.remove(&path) {
                     return Task::none();
                 }
-                self.sortby = by.clone();
-                self.sort_ascending = !self.sort_ascending;
-
-                let mut tree = self.tree.clone().unwrap_or(vec![]);
-                match by {
-                    SortBy::Name => tree.sort_by_key(|i| i.name.clone().to_lowercase()),
-                    SortBy::Size => tree.sort_by_key(|i| i.size.unwrap_or(0)),
-                    SortBy::CreatedAt => tree.sort_by_key(|i| i.created.unwrap_or(std::time::SystemTime::now())),
-                    SortBy::LastModified => tree.sort_by_key(|i| i.last_modified.unwrap_or(std::time::SystemTime::now())),
+                if let Some(handle) = handle {
+                    self.thumbnails.insert(path, handle);
                 }
-                tree.sort_by_key(|i| !i.is_dir);
-                if !self.sort_ascending {
-                    tree.reverse();
+                Task::none()
+            }
+            // Handled by the tabs and windows around the explorer
+            Message::OpenInNewTab(_) | Message::OpenInNewWindow(_) => Task::none(),
+            Message::ToggleBookmark => {
+                if let Some(i) = self.settings.bookmarks.iter().position(|b| b == &self.current_path) {
+                    self.settings.bookmarks.remove(i);
+                } else {
+                    self.settings.bookmarks.push(self.current_path.clone());
                 }
-                self.tree = Some(tree);
+                self.save_settings();
+                Task::none()
+            }
+            Message::Scrolled(offset) => {
+                self.scroll_offset = offset;
+                Task::none()
+            }
+            Message::FolderTreeToggled(data) => {
+                let task = self.folder_tree.toggle(data.path);
+                self.settings.expanded_folders = self.folder_tree.expanded_paths();
+                self.save_settings();
+                task
+            }
+            Message::FolderTreeLoaded(path, event) => {
+                self.folder_tree.apply(&path, event);
+                Task::none()
+            }
+            Message::ToggleExpanded(data) => {
+                if self.expanded.contains_key(&data.path) {
+                    // Collapsing also forgets everything expanded below it
+                    self.expanded.retain(|path, _| !path.starts_with(&data.path));
+                    return Task::none();
+                }
+                let (listing, task) = Listing::open(data.path.clone());
+                self.expanded.insert(data.path.clone(), listing);
+                task.map(move |event| Message::ExpandedLoaded(data.path.clone(), event))
+            }
+            Message::ExpandedLoaded(path, event) => {
+                if let Some(listing) = self.expanded.get_mut(&path) {
+                    if listing.is_current(&event) {
+                        listing.apply(event, &self.sort, self.settings.folders_first);
+                    }
+                }
+                Task::none()
+            }
+            Message::MillerSelect(index, data) => {
+                self.addressbar_focused = false;
+                // Everything right of the clicked column belongs to the old selection
+                self.miller.truncate(index);
+                match index.checked_sub(1).and_then(|i| self.miller.get_mut(i)) {
+                    Some(column) => column.selected = Some(data.path.clone()),
+                    None => self.highlighted_file = Some(data.path.clone()),
+                }
+                let preview = if self.settings.show_preview { self.preview.show(data.clone()) } else { Task::none() };
+
+                if data.is_dir {
+                    let (column, task) = MillerColumn::open(data.path.clone());
+                    self.miller.push(column);
+                    let index = self.miller.len();
+                    return Task::batch(vec![
+                        preview,
+                        task.map(move |event| Message::MillerLoaded(index, event)),
+                        scrollable::snap_to(self.miller_id.clone(), RelativeOffset { x: 1.0, y: 0.0 }),
+                    ]);
+                }
+                if data.file_type == Some(FileType::Image)
+                    && !self.thumbnails.contains_key(&data.path)
+                    && self.pending_thumbnails.insert(data.path.clone())
+                {
+                    return Task::batch(vec![
+                        preview,
+                        thumbnails::request(data.path, self.thumbnails_cancelled.clone()).map(|(path, handle)| Message::ThumbnailLoaded(path, handle)),
+                    ]);
+                }
+                preview
+            }
+            Message::MillerLoaded(index, event) => {
+                if let Some(column) = index.checked_sub(1).and_then(|i| self.miller.get_mut(i)) {
+                    if column.listing.is_current(&event) {
+                        column.listing.apply(event, &self.sort, self.settings.folders_first);
+                    }
+                }
+                Task::none()
+            }
+            Message::SortScopeToggled(per_directory) => {
+                self.settings.sort_scope = if per_directory { SortScope::PerDirectory } else { SortScope::Global };
+                self.settings.set_sort_for(&self.current_path, self.sort.clone());
+                self.save_settings();
+                Task::none()
+            }
+            Message::RestoreSessionToggled(restore) => {
+                self.settings.restore_session = restore;
+                self.save_settings();
+                Task::none()
+            }
+            Message::StartDirChosen => {
+                self.settings.start_dir = Some(self.current_path.clone());
+                self.save_settings();
                 Task::none()
             }
             Message::SelectFile(item) => {
                 self.addressbar_focused = false;
-                self.highlighted_file = Some(item.name.clone());
+                self.highlighted_file = Some(item.path.clone());
+                if self.settings.show_preview {
+                    return self.preview.show(item);
+                }
                 Task::none()
             }
             Message::EventOccurred(event) => {
@@ -183,6 +686,29 @@ impl Explorer {
                         self.addressbar_focused = false;
                         Task::none()
                     }
+                    Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                        if let Some(index) = self.resizing_column {
+                            self.settings.columns.resize(index, position.x - self.cursor.x);
+                        }
+                        self.cursor = position;
+                        Task::none()
+                    }
+                    Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                        if self.resizing_column.take().is_some() {
+                            self.save_settings();
+                        }
+                        if let Some(from) = self.dragging_column.take() {
+                            let to = self.hovered_column.unwrap_or(from);
+                            if from == to {
+                                // Released on the same header so it was a click
+                                let by = self.settings.columns.columns[from].kind.sort_by();
+                                return self.update(Message::SortTree(by));
+                            }
+                            self.settings.columns.reorder(from, to);
+                            self.save_settings();
+                        }
+                        Task::none()
+                    }
                     _ => Task::none(),
                 }
             }
@@ -195,7 +721,7 @@ impl Explorer {
                 if id == "addressbar".into() {
                     self.addressbar_content = self.current_path.to_string_lossy().to_string();
                     self.addressbar_focused = true;
-                    return text_input::focus("addressbar_inp").then(|_: Task<Message>| Task::from(text_input::select_all("addressbar_inp")));
+                    return text_input::focus(self.addressbar_id.clone()).chain(text_input::select_all(self.addressbar_id.clone()));
                 }
                 Task::none()
             }
@@ -216,7 +742,7 @@ impl Explorer {
                     self.history_index += 1;
                     self.addressbar_focused = false;
                     return Task::batch(vec![
-                        load_tree(self.current_path.clone()),
+                        self.load_tree(),
                         task::effect(Action::widget(operation::focusable::unfocus())),
                     ]);
                 }
@@ -228,32 +754,73 @@ impl Explorer {
     pub fn view(&self) -> Element<Message> {
         let mut col: Column<'_, Message> = Column::new().spacing(5);
 
-        let mut tree = self.tree.clone().unwrap_or(vec![]);
-        tree.insert(0, FileData::parent(self.current_path.clone()));
+        let mut tree = self.visible_tree();
+        let parent = FileData::parent(self.current_path.clone());
 
-
-        for data in tree {
-            col = col.push(
-                FileItem::from(data.clone())
-                    .is_highlighted(self.highlighted_file.clone().unwrap_or("".to_string()) == data.name)
-                    .on_select(Box::new(Message::SelectFile))
-                    .on_open(Box::new(Message::OpenFile))
-            );
+        if self.settings.group_by == GroupBy::None {
+            tree.insert(0, parent);
+            col = col.push(self.files_view(tree));
+        } else {
+            col = col.push(self.files_view(vec![parent]));
+            for group in group_files(&tree, self.settings.group_by) {
+                let collapsed = self.collapsed_groups.contains(&group.label);
+                col = col.push(
+                    button(
+                        text(format!(
+                            "{} {} ({} items, {})",
+                            if collapsed { "▸" } else { "▾" },
+                            group.label,
+                            group.items.len(),
+                            readable_size(group.total_size),
+                        ))
+                            .size(14)
+                            .shaping(text::Shaping::Advanced)
+                    )
+                        .on_press(Message::ToggleGroup(group.label.clone()))
+                        .padding(Padding::new(5.0).top(10))
+                        .style(button::text)
+                        .width(Length::Fill)
+                );
+                if !collapsed {
+                    col = col.push(self.files_view(group.items));
+                }
+            }
+        }
+        if self.loader.is_loading() {
+            col = col.push(text("Loading...").size(14));
         }
 
+        let list: Element<Message> = match self.settings.view_mode {
+            ViewMode::Columns => self.miller_view(),
+            view_mode => {
+                let list = if view_mode != ViewMode::Grid {
+                    column![
+                        container(self.tableheader())
+                            .width(self.list_width()),
+                    ]
+                        .push_maybe(self.column_chooser_open.then(|| self.column_chooser()))
+                } else {
+                    column![]
+                };
+                list
+                    .push(
+                        scrollable(col)
+                            .width(self.list_width())
+                            .on_scroll(|viewport| Message::Scrolled(viewport.relative_offset()))
+                            .id(self.list_id.clone()),
+                            // .height(Length::Fill)
+                    )
+                    .into()
+            }
+        };
+
         column![
             self.header(),
             row![
                 self.sidebar(),
-                column![
-                    container(self.tableheader())
-                        .width(self.width.unwrap_or(200.0) - 200.0),
-                    scrollable(col)
-                        .width(self.width.unwrap_or(200.0) - 200.0)
-                        .id("explorer"),
-                        // .height(Length::Fill)
-                ]
+                list,
             ]
+                .push_maybe(self.settings.show_preview.then(|| self.preview.view()))
                 .spacing(5),
         ]
             .padding(10)
@@ -261,11 +828,158 @@ impl Explorer {
             .into()
     }
 
+    // Lays the entries out as rows or as tiles depending on the view mode
+    fn files_view(&self, items: Vec<FileData>) -> Element<Message> {
+        match self.settings.view_mode {
+            ViewMode::List | ViewMode::Columns => {
+                Column::with_children(items.into_iter().map(|data| self.file_row(data)))
+                    .spacing(5)
+                    .into()
+            }
+            ViewMode::Tree => {
+                let mut rows = vec![];
+                self.tree_rows(&items, 0, &mut rows);
+                Column::with_children(rows)
+                    .spacing(5)
+                    .into()
+            }
+            ViewMode::Grid => {
+                let tile_width = self.settings.zoom + 10.0 + 10.0;
+                let available = self.list_width() - 25.0;
+                let per_row = ((available + 10.0) / tile_width).floor().max(1.0) as usize;
+
+                let mut grid = Column::new().spacing(10);
+                let mut items = items.into_iter().peekable();
+                while items.peek().is_some() {
+                    grid = grid.push(
+                        Row::with_children(items.by_ref().take(per_row).map(|data| self.file_tile(data)))
+                            .spacing(10)
+                    );
+                }
+                grid.into()
+            }
+        }
+    }
+
+    fn miller_view(&self) -> Element<Message> {
+        let root_selected = self.highlighted_file.clone();
+        let root = self.visible_tree();
+
+        let mut columns = Row::new()
+            .spacing(5)
+            .push(miller::column_view(&root, root_selected.as_ref(), 0));
+        for (i, column) in self.miller.iter().enumerate() {
+            columns = columns.push(column.view(i + 1));
+        }
+
+        // The last selection is a file so it gets previewed in place of another column
+        let selected = match self.miller.last() {
+            Some(column) => column.selected_data(),
+            None => root_selected.as_ref().and_then(|path| root.iter().find(|d| &d.path == path)),
+        };
+        if let Some(data) = selected.filter(|d| !d.is_dir) {
+            columns = columns.push(self.miller_preview(data));
+        }
+
+        scrollable(columns)
+            .direction(scrollable::Direction::Horizontal(scrollable::Scrollbar::default()))
+            .width(self.list_width())
+            .height(Length::Fill)
+            .id(self.miller_id.clone())
+            .into()
+    }
+
+    fn miller_preview(&self, data: &FileData) -> Element<Message> {
+        let image = match self.thumbnails.get(&data.path) {
+            Some(handle) => Image::new(handle.clone()),
+            None => Image::new(image_from_type(data.file_type.clone().unwrap_or(FileType::Unknown))),
+        };
+
+        let mut details = Column::new()
+            .spacing(5)
+            .push(container(image.width(Length::Fill)).height(200).center_x(Length::Fill))
+            .push(text(data.name.clone()).size(16));
+        for column in [ColumnKind::Size, ColumnKind::MimeType, ColumnKind::CreatedAt, ColumnKind::LastModified] {
+            details = details.push(text(format!("{}: {}", column, column.value(data))).size(14));
+        }
+
+        container(details)
+            .padding(10)
+            .width(miller::COLUMN_WIDTH * 1.5)
+            .into()
+    }
+
+    fn file_tile(&self, data: FileData) -> Element<Message> {
+        FileTile::new(data.clone(), self.settings.zoom)
+            .thumbnail(self.thumbnails.get(&data.path).cloned())
+            .is_highlighted(self.highlighted_file.as_ref() == Some(&data.path))
+            .on_select(Box::new(Message::SelectFile))
+            .on_open(Box::new(Message::OpenFile))
+            .on_middle_click(Box::new(Message::OpenInNewTab))
+            .into()
+    }
+
+    // Starts generating thumbnails for the images that don't have one yet
+    fn request_thumbnails(&mut self) -> Task<Message> {
+        if self.settings.view_mode != ViewMode::Grid {
+            return Task::none();
+        }
+
+        let mut tasks = vec![];
+        for data in self.tree.iter().flatten() {
+            if data.file_type != Some(FileType::Image)
+                || self.thumbnails.contains_key(&data.path)
+                || !self.pending_thumbnails.insert(data.path.clone())
+            {
+                continue;
+            }
+            tasks.push(thumbnails::request(data.path.clone(), self.thumbnails_cancelled.clone()).map(|(path, handle)| Message::ThumbnailLoaded(path, handle)));
+        }
+        Task::batch(tasks)
+    }
+
+    // Pushes a row for every entry followed by the children of the expanded directories
+    fn tree_rows<'a>(&'a self, items: &[FileData], depth: u16, rows: &mut Vec<Element<'a, Message>>) {
+        for data in items {
+            let listing = self.expanded.get(&data.path);
+            rows.push(
+                FileItem::from(data.clone())
+                    .columns(self.settings.columns.clone())
+                    .is_highlighted(self.highlighted_file.as_ref() == Some(&data.path))
+                    .on_select(Box::new(Message::SelectFile))
+                    .on_open(Box::new(Message::OpenFile))
+                    .on_middle_click(Box::new(Message::OpenInNewTab))
+                    .on_toggle(Box::new(Message::ToggleExpanded), depth, listing.is_some())
+                    .into()
+            );
+            if let Some(listing) = listing {
+                if listing.is_loading() && listing.tree.is_empty() {
+                    rows.push(
+                        container(text("Loading...").size(14))
+                            .padding(Padding::new(0.0).left(18.0 + (depth + 1) as f32 * 16.0))
+                            .into()
+                    );
+                }
+                self.tree_rows(&listing.tree, depth + 1, rows);
+            }
+        }
+    }
+
+    fn file_row(&self, data: FileData) -> Element<Message> {
+        FileItem::from(data.clone())
+            .columns(self.settings.columns.clone())
+            .is_highlighted(self.highlighted_file.as_ref() == Some(&data.path))
+            .on_select(Box::new(Message::SelectFile))
+            .on_open(Box::new(Message::OpenFile))
+            .on_middle_click(Box::new(Message::OpenInNewTab))
+            .into()
+    }
+
     pub fn sidebar(&self) -> Element<Message> {
         let mut sidebar = Column::new()
             .spacing(5);
 
-        for item in Platform::special_dirs() {
+        for item in Platform::special_dirs().into_iter().chain(self.settings.bookmarks.iter().filter(|b| b.is_dir()).cloned()) {
             sidebar = sidebar.push(
                 FileItem::new(item.clone())
                     .on_select(Box::new(Message::SelectFile))
@@ -273,6 +987,9 @@ impl Explorer {
                     .sidebar()
             );
         }
+        sidebar = sidebar
+            .push(Space::with_height(10))
+            .push(self.folder_tree.view(&self.current_path));
 
         scrollable(sidebar)
             .width(200)
@@ -282,41 +999,77 @@ impl Explorer {
 
     pub fn tableheader(&self) -> Element<Message> {
         let arrow = |column| {
-            if self.sortby == column {
-                if self.sort_ascending { "⏶" } else { "⏷" }
+            if self.sort.by == column {
+                if self.sort.ascending { "⏶" } else { "⏷" }
             } else {
                 ""
             }
         };
 
-        row![
-            Space::with_width(18),
-            button(text(format!("Name {}", arrow(SortBy::Name))).size(14).shaping(text::Shaping::Advanced))
-                .on_press(Message::SortTree(SortBy::Name))
-                .padding(0)
-                .style(button::text)
-                .width(Length::FillPortion(4)),
-            button(text(format!("Size {}", arrow(SortBy::Size))).size(14).shaping(text::Shaping::Advanced))
-                .on_press(Message::SortTree(SortBy::Size))
-                .padding(0)
-                .style(button::text)
-                .width(Length::FillPortion(1)),
-            button(text(format!("Created At {}", arrow(SortBy::CreatedAt))).size(14).shaping(text::Shaping::Advanced))
-                .on_press(Message::SortTree(SortBy::CreatedAt))
-                .padding(0)
-                .style(button::text)
-                .width(Length::FillPortion(2)),
-            button(text(format!("Last Modified {}", arrow(SortBy::LastModified))).size(14).shaping(text::Shaping::Advanced))
-                .on_press(Message::SortTree(SortBy::LastModified))
-                .padding(0)
-                .style(button::text)
-                .width(Length::FillPortion(2)),
-        ]
+        let mut header = Row::new()
+            .push(Space::with_width(18))
             .spacing(5)
-            .padding(5)
+            .padding(5);
+
+        for (i, column) in self.settings.columns.columns.iter().enumerate() {
+            let title = text(format!("{} {}", column.kind, arrow(column.kind.sort_by())))
+                .size(14)
+                .shaping(text::Shaping::Advanced)
+                .width(Length::Fill);
+            // Dragging the title reorders, dragging the right edge resizes
+            let handle = mouse_area(
+                container(Space::new(1, 14))
+                    .padding(Padding::new(0.0).left(2).right(1))
+                    .style(|_: &_| container::Style {
+                        background: Some(Color::from_rgba(1.0, 1.0, 1.0, 0.1).into()),
+                        ..Default::default()
+                    })
+            )
+                .on_press(Message::ColumnResizeStarted(i))
+                .interaction(mouse::Interaction::ResizingHorizontally);
+
+            header = header.push(
+                row![
+                    mouse_area(title)
+                        .on_press(Message::ColumnDragStarted(i))
+                        .on_enter(Message::ColumnHovered(i))
+                        .interaction(mouse::Interaction::Pointer),
+                    handle,
+                ]
+                    .width(column.width)
+            );
+        }
+
+        header
+            .push(
+                button(text("⋮").size(14).shaping(text::Shaping::Advanced))
+                    .on_press(Message::ToggleColumnChooser)
+                    .padding(0)
+                    .style(button::text)
+            )
             .into()
     }
 
+    pub fn column_chooser(&self) -> Element<Message> {
+        let mut chooser = Row::new()
+            .spacing(10)
+            .padding(5);
+
+        for column in ColumnKind::ALL {
+            let toggle = checkbox(column.to_string(), self.settings.columns.contains(column))
+                .size(14)
+                .text_size(14);
+            // The name column is always shown
+            chooser = chooser.push(if column == ColumnKind::Name {
+                toggle
+            } else {
+                toggle.on_toggle(move |shown| Message::ColumnToggled(column, shown))
+            });
+        }
+
+        chooser.wrap().into()
+    }
+
     pub fn header(&self) -> Element<Message> {
         container(
             row![
@@ -341,8 +1094,61 @@ impl Explorer {
                     .padding(0)
                     .width(20),
                 self.addressbar(),
-                // todo search
+                button(text(if self.settings.bookmarks.contains(&self.current_path) { "★" } else { "☆" }).size(16).shaping(text::Shaping::Advanced))
+                    .on_press(Message::ToggleBookmark)
+                    .padding(Padding::new(0.0).top(3))
+                    .style(button::text),
+                button(text("⧉").size(16).shaping(text::Shaping::Advanced))
+                    .on_press(Message::OpenInNewWindow(self.current_path.clone()))
+                    .padding(Padding::new(0.0).top(3))
+                    .style(button::text),
+                pick_list(SortBy::ALL, Some(self.sort.by.clone()), Message::SortTree)
+                    .text_size(14)
+                    .padding(Padding::new(2.0).left(5).right(5)),
+                pick_list(SortBy::ALL, Some(self.sort.then_by.clone()), Message::SecondarySortChanged)
+                    .placeholder("Then by")
+                    .text_size(14)
+                    .padding(Padding::new(2.0).left(5).right(5)),
+                pick_list(ViewMode::ALL, Some(self.settings.view_mode), Message::ViewModeChanged)
+                    .text_size(14)
+                    .padding(Padding::new(2.0).left(5).right(5)),
+                pick_list(GroupBy::ALL, Some(self.settings.group_by), Message::GroupByChanged)
+                    .text_size(14)
+                    .padding(Padding::new(2.0).left(5).right(5)),
+                checkbox("Folders first", self.settings.folders_first)
+                    .on_toggle(Message::FoldersFirstToggled)
+                    .size(14)
+                    .text_size(14),
+                checkbox("Sort per folder", self.settings.sort_scope == SortScope::PerDirectory)
+                    .on_toggle(Message::SortScopeToggled)
+                    .size(14)
+                    .text_size(14),
+                button(text(if self.settings.show_preview { "Hide preview" } else { "Preview" }).size(14))
+                    .on_press(Message::TogglePreview)
+                    .padding(Padding::new(2.0).left(5).right(5))
+                    .style(button::text),
+                checkbox("Restore session", self.settings.restore_session)
+                    .on_toggle(Message::RestoreSessionToggled)
+                    .size(14)
+                    .text_size(14),
+                text_input("Search", &self.search)
+                    .on_input(Message::SearchChanged)
+                    .size(14)
+                    .padding(Padding::new(2.0).left(5).right(5))
+                    .width(200),
             ]
+                .push_maybe((!self.settings.restore_session).then(|| {
+                    let is_start = self.settings.start_dir() == self.current_path;
+                    button(text(if is_start { "Start folder" } else { "Start here" }).size(14))
+                        .on_press_maybe((!is_start).then_some(Message::StartDirChosen))
+                        .padding(Padding::new(2.0).left(5).right(5))
+                        .style(button::text)
+                }))
+                .push_maybe((self.settings.view_mode == ViewMode::Grid).then(|| {
+                    slider(48.0..=256.0, self.settings.zoom, Message::ZoomChanged)
+                        .on_release(Message::ZoomReleased)
+                        .width(120)
+                }))
                 .spacing(5)
                 .padding(5)
                 .height(Length::Fill)
@@ -371,7 +1177,7 @@ impl Explorer {
                 text_input(Platform::home_dir().to_string_lossy().as_ref(), &self.addressbar_content)
                     .on_input(Message::AddressbarChanged)
                     .on_submit(Message::DirChanged)
-                    .id("addressbar_inp")
+                    .id(self.addressbar_id.clone())
             )
                 .width(Length::Fill)
                 .into()
@@ -381,11 +1187,4 @@ impl Explorer {
     pub fn title(&self) -> String {
         format!("Filed - {}", self.current_path.to_string_lossy())
     }
-
-    pub fn subscription(&self) -> Subscription<Message> {
-        Subscription::batch(vec![
-            window::resize_events().map(|(_id, size)| Message::WindowResized(size)),
-            event::listen().map(|event| Message::EventOccurred(event)),
-        ])
-    }
 }
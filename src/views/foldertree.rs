@@ -0,0 +1,118 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use iced::widget::Column;
+use iced::{Element, Task};
+
+use crate::components::fileitem::{FileData, FileItem};
+use crate::loader::{LoadEvent, Listing};
+use crate::platform::Platform;
+use crate::sorting::Sort;
+use crate::views::explorer::Message;
+
+// Lazily loaded directory tree shown in the sidebar, rooted at home and the filesystem root
+#[derive(Default)]
+pub struct FolderTree {
+    nodes: HashMap<PathBuf, Listing>,
+    // Nodes expanded to show the current directory rather than by hand
+    revealed: HashSet<PathBuf>,
+}
+
+impl FolderTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn roots() -> Vec<PathBuf> {
+        let home = Platform::home_dir();
+        let root = home.ancestors().last().map(Path::to_path_buf).unwrap_or(home.clone());
+        if home == root { vec![root] } else { vec![home, root] }
+    }
+
+    // Expands the given directories again, skipping the ones that no longer exist
+    pub fn restore(&mut self, paths: &[PathBuf]) -> Task<Message> {
+        Task::batch(paths.iter().filter(|p| p.is_dir()).map(|p| self.expand(p.clone())))
+    }
+
+    pub fn expanded_paths(&self) -> Vec<PathBuf> {
+        self.nodes.keys().filter(|p| !self.revealed.contains(*p)).cloned().collect()
+    }
+
+    pub fn expand(&mut self, path: PathBuf) -> Task<Message> {
+        if self.nodes.contains_key(&path) {
+            return Task::none();
+        }
+        let (listing, task) = Listing::open_dirs(path.clone());
+        self.nodes.insert(path.clone(), listing);
+        task.map(move |event| Message::FolderTreeLoaded(path.clone(), event))
+    }
+
+    pub fn toggle(&mut self, path: PathBuf) -> Task<Message> {
+        // A revealed node toggled by hand is kept like any other
+        self.revealed.remove(&path);
+        if self.nodes.contains_key(&path) {
+            self.nodes.retain(|p, _| !p.starts_with(&path));
+            self.revealed.retain(|p| !p.starts_with(&path));
+            Task::none()
+        } else {
+            self.expand(path)
+        }
+    }
+
+    // Expands every ancestor of `path` below the closest root so its node is visible,
+    // collapsing what was expanded for the previous directory
+    pub fn reveal(&mut self, path: &Path) -> Task<Message> {
+        let ancestors = match Self::roots().into_iter().find(|root| path.starts_with(root)) {
+            Some(root) => path.ancestors()
+                .skip(1)
+                .take_while(|a| a.starts_with(&root))
+                .map(Path::to_path_buf)
+                .collect::<Vec<_>>(),
+            None => vec![],
+        };
+
+        let stale = self.revealed.iter().filter(|p| !ancestors.contains(p)).cloned().collect::<Vec<_>>();
+        for path in stale {
+            self.revealed.remove(&path);
+            self.nodes.remove(&path);
+        }
+
+        let hidden = ancestors.into_iter().filter(|a| !self.nodes.contains_key(a)).collect::<Vec<_>>();
+        self.revealed.extend(hidden.iter().cloned());
+        Task::batch(hidden.into_iter().map(|a| self.expand(a)).collect::<Vec<_>>())
+    }
+
+    pub fn apply(&mut self, path: &PathBuf, event: LoadEvent) {
+        if let Some(listing) = self.nodes.get_mut(path) {
+            if listing.is_current(&event) {
+                listing.apply(event, &Sort::default(), true);
+            }
+        }
+    }
+
+    pub fn view(&self, current_path: &PathBuf) -> Element<Message> {
+        let mut rows = vec![];
+        for root in Self::roots() {
+            self.rows(FileData::lazy(root, true), 0, current_path, &mut rows);
+        }
+        Column::with_children(rows).spacing(5).into()
+    }
+
+    fn rows<'a>(&'a self, data: FileData, depth: u16, current_path: &PathBuf, rows: &mut Vec<Element<'a, Message>>) {
+        let listing = self.nodes.get(&data.path);
+        rows.push(
+            FileItem::from(data.clone())
+                .sidebar()
+                .is_highlighted(&data.path == current_path)
+                .on_select(Box::new(Message::OpenFile))
+                .on_toggle(Box::new(Message::FolderTreeToggled), depth, listing.is_some())
+                .into()
+        );
+
+        if let Some(listing) = listing {
+            for child in listing.tree.iter().filter(|d| d.is_dir) {
+                self.rows(child.clone(), depth + 1, current_path, rows);
+            }
+        }
+    }
+}
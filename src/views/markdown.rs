@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use iced::font::{Style, Weight};
+use iced::widget::image::Handle;
+use iced::widget::{button, container, horizontal_rule, row, scrollable, text, Column, Image, Row};
+use iced::{Element, Font, Padding, Theme};
+
+use crate::markdown::{Block, Span};
+use crate::views::explorer::Message;
+use crate::views::preview::code;
+
+const TEXT_SIZE: f32 = 13.0;
+const CELL_WIDTH: f32 = 120.0;
+
+// Renders a parsed Markdown document, links send `Message::OpenLink`
+pub fn view<'a>(blocks: &'a [Block], images: &'a HashMap<PathBuf, Handle>) -> Element<'a, Message> {
+    Column::with_children(blocks.iter().map(|block| block_view(block, images)))
+        .spacing(8)
+        .into()
+}
+
+fn block_view<'a>(block: &'a Block, images: &'a HashMap<PathBuf, Handle>) -> Element<'a, Message> {
+    match block {
+        Block::Heading(level, spans) => {
+            let size = match level {
+                1 => 22.0,
+                2 => 19.0,
+                3 => 16.0,
+                _ => 14.0,
+            };
+            inline(spans, images, size, true)
+        }
+        Block::Paragraph(spans) => inline(spans, images, TEXT_SIZE, false),
+        Block::Quote(spans) => {
+            container(inline(spans, images, TEXT_SIZE, false))
+                .padding(Padding::new(5.0).left(10))
+                .style(shaded)
+                .into()
+        }
+        Block::ListItem(depth, marker, spans) => {
+            row![
+                text(marker.clone()).size(TEXT_SIZE).shaping(text::Shaping::Advanced),
+                inline(spans, images, TEXT_SIZE, false),
+            ]
+                .spacing(5)
+                .padding(Padding::new(0.0).left(*depth as f32 * 16.0))
+                .into()
+        }
+        Block::Code(_, lines) => {
+            container(scrollable(code(lines, false)).direction(scrollable::Direction::Horizontal(scrollable::Scrollbar::default())))
+                .padding(5)
+                .style(shaded)
+                .into()
+        }
+        Block::Table(header, rows) => {
+            let table = Column::with_children(
+                std::iter::once(table_row(header, images, true))
+                    .chain(rows.iter().map(|cells| table_row(cells, images, false)))
+            )
+                .spacing(4);
+            scrollable(table)
+                .direction(scrollable::Direction::Horizontal(scrollable::Scrollbar::default()))
+                .into()
+        }
+        Block::Rule => horizontal_rule(1).into(),
+    }
+}
+
+fn table_row<'a>(cells: &'a [Vec<Span>], images: &'a HashMap<PathBuf, Handle>, header: bool) -> Element<'a, Message> {
+    Row::with_children(cells.iter().map(|spans| {
+        container(inline(spans, images, TEXT_SIZE, header))
+            .width(CELL_WIDTH)
+            .into()
+    }))
+        .spacing(8)
+        .into()
+}
+
+// Lays the words out like a paragraph, images get a line of their own
+fn inline<'a>(spans: &'a [Span], images: &'a HashMap<PathBuf, Handle>, size: f32, bold: bool) -> Element<'a, Message> {
+    let mut lines: Vec<Element<'a, Message>> = vec![];
+    let mut words: Vec<Element<'a, Message>> = vec![];
+
+    let flush = |words: &mut Vec<Element<'a, Message>>, lines: &mut Vec<Element<'a, Message>>| {
+        if !words.is_empty() {
+            lines.push(Row::with_children(std::mem::take(words)).spacing(size * 0.3).wrap().into());
+        }
+    };
+
+    for span in spans {
+        if let Some(handle) = span.image.as_ref().and_then(|path| images.get(path)) {
+            flush(&mut words, &mut lines);
+            lines.push(Image::new(handle.clone()).into());
+            continue;
+        }
+
+        let font = Font {
+            weight: if bold || span.bold { Weight::Bold } else { Weight::Normal },
+            style: if span.italic { Style::Italic } else { Style::Normal },
+            ..if span.code { Font::MONOSPACE } else { Font::DEFAULT }
+        };
+        let plain = !bold && !span.bold && !span.italic && !span.code;
+
+        for word in span.text.split_whitespace() {
+            let mut word = text(word.to_owned()).size(size).shaping(text::Shaping::Advanced);
+            if !plain {
+                word = word.font(font);
+            }
+            words.push(match &span.link {
+                Some(link) => {
+                    button(word.style(|theme: &Theme| text::Style { color: Some(theme.palette().primary) }))
+                        .on_press(Message::OpenLink(link.clone()))
+                        .padding(0)
+                        .style(button::text)
+                        .into()
+                }
+                None if span.code => container(word).style(shaded).into(),
+                None => word.into(),
+            });
+        }
+    }
+    flush(&mut words, &mut lines);
+
+    Column::with_children(lines).spacing(5).into()
+}
+
+fn shaded(theme: &Theme) -> container::Style {
+    container::Style {
+        background: Some(theme.extended_palette().background.weak.color.into()),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+use iced::widget::{scrollable, Column};
+use iced::{Element, Task};
+
+use crate::columns::{ColumnKind, ColumnLayout};
+use crate::components::fileitem::{FileData, FileItem};
+use crate::loader::{LoadEvent, Listing};
+use crate::views::explorer::Message;
+
+pub const COLUMN_WIDTH: f32 = 250.0;
+
+// One column of the column browser, listing the children of `listing.path`
+pub struct MillerColumn {
+    pub listing: Listing,
+    pub selected: Option<PathBuf>,
+}
+
+impl MillerColumn {
+    pub fn open(path: PathBuf) -> (Self, Task<LoadEvent>) {
+        let (listing, task) = Listing::open(path);
+        (Self { listing, selected: None }, task)
+    }
+
+    pub fn selected_data(&self) -> Option<&FileData> {
+        self.selected.as_ref().and_then(|selected| self.listing.tree.iter().find(|d| &d.path == selected))
+    }
+
+    // `index` is the position of this column in the browser
+    pub fn view(&self, index: usize) -> Element<Message> {
+        column_view(&self.listing.tree, self.selected.as_ref(), index)
+    }
+}
+
+// Renders a list of entries with the regular file rows, shared by the first column
+pub fn column_view<'a>(tree: &[FileData], selected: Option<&PathBuf>, index: usize) -> Element<'a, Message> {
+    let rows = tree.iter().map(|data| {
+        FileItem::from(data.clone())
+            .columns(ColumnLayout::only(ColumnKind::Name, COLUMN_WIDTH - 40.0))
+            .is_highlighted(selected == Some(&data.path))
+            .on_select(Box::new(move |data| Message::MillerSelect(index, data)))
+            .on_open(Box::new(Message::OpenFile))
+            .into()
+    });
+
+    scrollable(Column::with_children(rows).spacing(5))
+        .width(COLUMN_WIDTH)
+        .into()
+}
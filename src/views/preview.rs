@@ -0,0 +1,484 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+
+use iced::font;
+use iced::mouse::ScrollDelta;
+use iced::widget::{button, column, container, mouse_area, row, scrollable, text, text_input, Column, Image, Row};
+use iced::{alignment, Color, Element, Font, Length, Task, Theme};
+use memmap2::Mmap;
+
+use crate::columns::ColumnKind;
+use crate::components::fileitem::FileData;
+use crate::hex::{self, BYTES_PER_ROW, PAGE_SIZE};
+use crate::highlight::Token;
+use crate::preview::{Content, Preview, PreviewLoader, STRUCTURED_LIMIT};
+use crate::utils::readable_size;
+use crate::views::explorer::Message;
+use crate::views::{dataview, markdown};
+
+pub const PREVIEW_WIDTH: f32 = 320.0;
+
+#[derive(Debug, Clone)]
+pub enum HexMessage {
+    // Jumps to an offset, it's aligned and clamped to the file
+    Offset(i64),
+    Scrolled(ScrollDelta),
+    GotoChanged(String),
+    Goto,
+    SearchChanged(String),
+    Search,
+    // Id of the search, and where the match starts and how long it is
+    Found(u64, Option<(u64, usize)>),
+}
+
+// Position and search of the hex view, reset for every file
+#[derive(Default)]
+struct HexState {
+    offset: u64,
+    // Rows scrolled by a touchpad that didn't add up to a whole one yet
+    scrolled: f32,
+    goto: String,
+    search: String,
+    found: Option<(u64, usize)>,
+    status: Option<String>,
+    search_id: u64,
+    searching: Option<Arc<AtomicBool>>,
+}
+
+impl HexState {
+    fn cancel_search(&mut self) {
+        if let Some(cancelled) = self.searching.take() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+// Panel right of the file list showing the selected file
+#[derive(Default)]
+pub struct PreviewPane {
+    loader: PreviewLoader,
+    preview: Option<Preview>,
+    hex: HexState,
+    // Show Markdown as source instead of rendering it
+    pub markdown_raw: bool,
+    // Tree nodes expanded or collapsed by hand, by their path
+    toggled: HashSet<String>,
+    // Fonts registered with the renderer and the descriptor that picks them, by file and modification time
+    fonts: HashMap<(PathBuf, Option<SystemTime>), Font>,
+}
+
+impl PreviewPane {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Starts loading the preview of `data`, the previous one stays visible until it's ready
+    pub fn show(&mut self, data: FileData) -> Task<Message> {
+        if !self.loader.is_loading() && self.preview.as_ref().is_some_and(|p| p.data.path == data.path) {
+            return Task::none();
+        }
+        self.loader.start(data, 1).map(|(id, preview)| Message::PreviewLoaded(id, preview))
+    }
+
+    // Reloads the current preview with one more page of text
+    pub fn load_more(&mut self) -> Task<Message> {
+        let Some(preview) = &self.preview else { return Task::none() };
+        let (data, pages) = (preview.data.clone(), preview.pages + 1);
+        self.loader.start(data, pages).map(|(id, preview)| Message::PreviewLoaded(id, preview))
+    }
+
+    pub fn apply(&mut self, id: u64, preview: Option<Preview>) -> Task<Message> {
+        if !self.loader.is_current(id) {
+            return Task::none();
+        }
+        self.loader.finish();
+        if self.preview.as_ref().map(|p| &p.data.path) != preview.as_ref().map(|p| &p.data.path) {
+            self.hex.cancel_search();
+            self.hex = HexState::default();
+            self.toggled.clear();
+        }
+        self.preview = preview;
+
+        // A font is registered once per version of the file, the renderer keeps it until the app exits
+        let Some(Preview { data, content: Content::Font { info, data: bytes, renderable: true, modified }, .. }) = &self.preview else {
+            return Task::none();
+        };
+        if self.fonts.contains_key(&(data.path.clone(), *modified)) {
+            return Task::none();
+        }
+        let descriptor = Font {
+            family: font::Family::Name(family_name(&info.family)),
+            weight: weight(info.weight),
+            style: if info.italic { font::Style::Italic } else { font::Style::Normal },
+            ..Font::DEFAULT
+        };
+        let (path, modified) = (data.path.clone(), *modified);
+        font::load(bytes.as_ref().clone()).map(move |result| Message::FontLoaded(path.clone(), modified, result.ok().map(|_| descriptor)))
+    }
+
+    pub fn font_loaded(&mut self, path: PathBuf, modified: Option<SystemTime>, font: Option<Font>) {
+        match font {
+            Some(font) => {
+                self.fonts.insert((path, modified), font);
+            }
+            None => println!("Failed to load font {}", path.display()),
+        }
+    }
+
+    pub fn toggle_node(&mut self, path: String) {
+        if !self.toggled.remove(&path) {
+            self.toggled.insert(path);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.loader.cancel();
+        self.hex.cancel_search();
+        self.hex = HexState::default();
+        self.toggled.clear();
+        self.preview = None;
+    }
+
+    pub fn update_hex(&mut self, message: HexMessage) -> Task<Message> {
+        let Some(Content::Hex(map)) = self.preview.as_ref().map(|p| &p.content) else { return Task::none() };
+        let map = map.clone();
+        let len = map.len() as u64;
+        let hex = &mut self.hex;
+
+        match message {
+            HexMessage::Offset(offset) => {
+                hex.offset = hex::clamp_offset(offset, len);
+            }
+            HexMessage::Scrolled(delta) => {
+                hex.scrolled += match delta {
+                    ScrollDelta::Lines { y, .. } => y * 3.0,
+                    ScrollDelta::Pixels { y, .. } => y / 16.0,
+                };
+                let rows = hex.scrolled.trunc();
+                hex.scrolled -= rows;
+                hex.offset = hex::clamp_offset(hex.offset as i64 - rows as i64 * BYTES_PER_ROW as i64, len);
+            }
+            HexMessage::GotoChanged(goto) => hex.goto = goto,
+            HexMessage::Goto => match hex::parse_offset(&hex.goto) {
+                Some(offset) if offset < len => {
+                    hex.offset = hex::clamp_offset(offset as i64, len);
+                    hex.status = None;
+                }
+                _ => hex.status = Some(format!("Offset must be below {} (0x{:x})", len, len)),
+            },
+            HexMessage::SearchChanged(search) => hex.search = search,
+            HexMessage::Search => {
+                let Some(pattern) = hex::parse_pattern(&hex.search) else { return Task::none() };
+                hex.cancel_search();
+                let cancelled = Arc::new(AtomicBool::new(false));
+                hex.searching = Some(cancelled.clone());
+                hex.status = Some("Searching...".to_owned());
+                // Continues after the previous match
+                let from = hex.found.map(|(pos, _)| pos + 1).unwrap_or(hex.offset);
+                hex.found = None;
+                hex.search_id += 1;
+                let (id, pattern_len) = (hex.search_id, pattern.len());
+                return hex::search(map, pattern, from, cancelled)
+                    .map(move |found| Message::Hex(HexMessage::Found(id, found.map(|pos| (pos, pattern_len)))));
+            }
+            HexMessage::Found(id, found) => {
+                if id != hex.search_id || hex.searching.take().is_none() {
+                    return Task::none();
+                }
+                match found {
+                    Some((pos, pattern_len)) => {
+                        hex.found = Some((pos, pattern_len));
+                        hex.offset = hex::clamp_offset(pos as i64, len);
+                        hex.status = Some(format!("Found at 0x{:x}", pos));
+                    }
+                    None => hex.status = Some("Not found".to_owned()),
+                }
+            }
+        }
+        Task::none()
+    }
+
+    fn load_more_button(&self) -> Element<Message> {
+        button(text(if self.loader.is_loading() { "Loading..." } else { "Load more" }).size(12))
+            .on_press_maybe((!self.loader.is_loading()).then_some(Message::PreviewLoadMore))
+            .padding(0)
+            .style(button::text)
+            .into()
+    }
+
+    fn hex_view(&self, map: &Mmap) -> Element<Message> {
+        let hex = &self.hex;
+        let len = map.len() as u64;
+        let last = hex::clamp_offset(i64::MAX, len);
+        let offset = hex.offset as i64;
+
+        let navigation = row![
+            nav_button("⏮", HexMessage::Offset(0)),
+            nav_button("◀", HexMessage::Offset(offset - PAGE_SIZE as i64)),
+            nav_button("▶", HexMessage::Offset(offset + PAGE_SIZE as i64)),
+            nav_button("⏭", HexMessage::Offset(last as i64)),
+            text(format!("0x{:x} / 0x{:x}", hex.offset, len)).size(12),
+        ]
+            .spacing(5)
+            .align_y(alignment::Vertical::Center);
+
+        let inputs = row![
+            text_input("Go to offset", &hex.goto)
+                .on_input(|goto| Message::Hex(HexMessage::GotoChanged(goto)))
+                .on_submit(Message::Hex(HexMessage::Goto))
+                .size(12),
+            text_input("Find bytes or text", &hex.search)
+                .on_input(|search| Message::Hex(HexMessage::SearchChanged(search)))
+                .on_submit(Message::Hex(HexMessage::Search))
+                .size(12),
+        ]
+            .spacing(5);
+
+        // Only the visible page is touched so the OS only reads those bytes
+        let end = (hex.offset + PAGE_SIZE).min(len);
+        let rows = (hex.offset..end).step_by(BYTES_PER_ROW as usize).map(|row_offset| {
+            let row_end = (row_offset + BYTES_PER_ROW).min(len);
+            let matched = hex.found.is_some_and(|(pos, n)| pos < row_end && pos + n as u64 > row_offset);
+            text(hex::row(&map[row_offset as usize..row_end as usize], row_offset))
+                .size(11)
+                .font(Font::MONOSPACE)
+                .style(move |theme: &Theme| text::Style {
+                    color: matched.then(|| theme.extended_palette().primary.base.color),
+                })
+                .into()
+        });
+
+        column![navigation, inputs]
+            .push_maybe(hex.status.as_ref().map(|status| text(status.clone()).size(12)))
+            .push(
+                mouse_area(Column::with_children(rows))
+                    .on_scroll(|delta| Message::Hex(HexMessage::Scrolled(delta)))
+            )
+            .spacing(5)
+            .into()
+    }
+
+    pub fn view(&self) -> Element<Message> {
+        let Some(preview) = &self.preview else {
+            let message = if self.loader.is_loading() { "Loading..." } else { "Select a file to preview it" };
+            return container(text(message).size(14))
+                .width(PREVIEW_WIDTH)
+                .center_x(PREVIEW_WIDTH)
+                .into();
+        };
+
+        let content: Option<Element<Message>> = match &preview.content {
+            Content::Image { handle, exif } => Some(
+                column![Image::new(handle.clone()).width(Length::Fill)]
+                    .push_maybe(exif.as_ref().map(|exif| fields(exif.fields())))
+                    .spacing(10)
+                    .into()
+            ),
+            Content::Text { text: content, truncated } => Some(
+                column![
+                    text(content.clone()).size(12).font(Font::MONOSPACE),
+                ]
+                    .push_maybe(truncated.then(|| self.load_more_button()))
+                    .spacing(5)
+                    .into()
+            ),
+            Content::Code { language, lines, truncated } => Some(
+                column![
+                    text(*language).size(12),
+                    code(lines, true),
+                ]
+                    .push_maybe(truncated.then(|| self.load_more_button()))
+                    .spacing(5)
+                    .into()
+            ),
+            Content::Markdown { source, blocks, images, truncated } => Some(
+                column![
+                    button(text(if self.markdown_raw { "Rendered" } else { "Raw" }).size(12))
+                        .on_press(Message::MarkdownRawToggled)
+                        .padding(0)
+                        .style(button::text),
+                    if self.markdown_raw {
+                        text(source.clone()).size(12).font(Font::MONOSPACE).into()
+                    } else {
+                        markdown::view(blocks, images)
+                    },
+                ]
+                    .push_maybe(truncated.then(|| self.load_more_button()))
+                    .spacing(5)
+                    .into()
+            ),
+            Content::Table { table, truncated } => Some(
+                column![dataview::table_view(table)]
+                    .push_maybe(truncated.then(|| self.load_more_button()))
+                    .spacing(5)
+                    .into()
+            ),
+            Content::Structured { format, root, source } => Some(
+                column![
+                    text(format.to_string()).size(12),
+                    match root {
+                        Some(Ok(root)) => dataview::tree_view(root, &self.toggled),
+                        Some(Err(error)) => dataview::error_view(source, error),
+                        None => text(format!("Larger than {}, too big to show as a tree", readable_size(STRUCTURED_LIMIT as u64))).size(12).into(),
+                    },
+                ]
+                    .spacing(5)
+                    .into()
+            ),
+            Content::Audio { tags, cover } => Some(
+                column![]
+                    .push_maybe(cover.as_ref().map(|cover| Image::new(cover.clone()).width(Length::Fill)))
+                    .push(fields(tags.fields()))
+                    .spacing(10)
+                    .into()
+            ),
+            Content::Video(info) => Some(fields(info.fields())),
+            Content::Document(info) => Some(fields(info.fields())),
+            Content::Font { info, renderable, modified, .. } => {
+                let sample: Element<Message> = match self.fonts.get(&(preview.data.path.clone(), *modified)) {
+                    Some(font) => Column::with_children([14.0, 20.0, 28.0, 40.0].map(|size| {
+                        text(info.sample).size(size).font(*font).shaping(text::Shaping::Advanced).into()
+                    }))
+                        // The renderer picks fonts by name, weight and style only
+                        .push(text("An installed font or another previewed file with the same name and style may be shown instead").size(10))
+                        .spacing(5)
+                        .into(),
+                    None if !renderable => text("The outlines of this font are stored in a way the preview can't unpack").size(12).into(),
+                    None => text("Loading font...").size(12).into(),
+                };
+                Some(column![sample, fields(info.fields())].spacing(10).into())
+            }
+            Content::Hex(map) => Some(self.hex_view(map)),
+            Content::Empty => None,
+        };
+
+        scrollable(
+            column![]
+                .push_maybe(content)
+                .push(text(preview.data.name.clone()).size(16))
+                .push(metadata(&preview.data))
+                .spacing(10)
+                .padding(5)
+        )
+            .width(PREVIEW_WIDTH)
+            .height(Length::Fill)
+            .into()
+    }
+}
+
+fn nav_button(label: &'static str, message: HexMessage) -> Element<'static, Message> {
+    button(text(label).size(14).shaping(text::Shaping::Advanced))
+        .on_press(Message::Hex(message))
+        .padding(0)
+        .style(button::text)
+        .into()
+}
+
+// Highlighted lines, with their numbers in front if `numbers` is set
+pub fn code(lines: &[Vec<(String, Token)>], numbers: bool) -> Element<Message> {
+    let digits = lines.len().max(1).to_string().len() as f32;
+    let rows = lines.iter().enumerate().map(|(i, spans)| {
+        let number = numbers.then(|| {
+            text((i + 1).to_string())
+                .size(12)
+                .font(Font::MONOSPACE)
+                .width(digits * 8.0)
+                .align_x(alignment::Horizontal::Right)
+                .style(|theme: &Theme| text::Style { color: Some(token_color(Token::Comment, theme)) })
+        });
+        // Empty lines still need their height
+        let spans: Vec<Element<Message>> = if spans.is_empty() {
+            vec![text(" ").size(12).font(Font::MONOSPACE).into()]
+        } else {
+            spans.iter().map(|(content, token)| {
+                let token = *token;
+                text(content.clone())
+                    .size(12)
+                    .font(Font::MONOSPACE)
+                    .style(move |theme: &Theme| text::Style { color: Some(token_color(token, theme)) })
+                    .into()
+            }).collect()
+        };
+        Row::new()
+            .push_maybe(number)
+            .push(Row::with_children(spans))
+            .spacing(10)
+            .into()
+    });
+    Column::with_children(rows).into()
+}
+
+// Colours come from the app theme so code looks at home in light and dark themes
+fn token_color(token: Token, theme: &Theme) -> Color {
+    let palette = theme.extended_palette();
+    match token {
+        Token::Plain => palette.background.base.text,
+        Token::Keyword => palette.primary.base.color,
+        Token::Type => palette.primary.strong.color,
+        Token::String => palette.success.base.color,
+        Token::Number => palette.danger.base.color,
+        Token::Comment => palette.background.strong.color,
+    }
+}
+
+// Fonts are looked up by a static family name, so each distinct one is leaked once
+fn family_name(family: &str) -> &'static str {
+    static NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    let mut names = NAMES.get_or_init(Default::default).lock().unwrap();
+    match names.get(family) {
+        Some(name) => name,
+        None => {
+            let name: &'static str = Box::leak(family.to_owned().into_boxed_str());
+            names.insert(name);
+            name
+        }
+    }
+}
+
+// The closest named weight to a numeric one
+fn weight(weight: u16) -> font::Weight {
+    match weight {
+        0..=149 => font::Weight::Thin,
+        150..=249 => font::Weight::ExtraLight,
+        250..=349 => font::Weight::Light,
+        350..=449 => font::Weight::Normal,
+        450..=549 => font::Weight::Medium,
+        550..=649 => font::Weight::Semibold,
+        650..=749 => font::Weight::Bold,
+        750..=849 => font::Weight::ExtraBold,
+        _ => font::Weight::Black,
+    }
+}
+
+// Labelled values read from the file, like EXIF data, song tags or document properties
+fn fields(fields: Vec<(&'static str, String)>) -> Element<'static, Message> {
+    let rows = fields.into_iter().map(|(label, value)| {
+        row![
+            text(label).size(12).width(100),
+            text(value).size(12).shaping(text::Shaping::Advanced),
+        ]
+            .spacing(5)
+            .into()
+    });
+    Column::with_children(rows).spacing(3).into()
+}
+
+// Every column value that is known for the file
+fn metadata(data: &FileData) -> Element<Message> {
+    let rows = ColumnKind::ALL.iter()
+        .filter(|kind| **kind != ColumnKind::Name)
+        .map(|kind| (kind, kind.value(data)))
+        .filter(|(_, value)| !value.is_empty() && value != "-")
+        .map(|(kind, value)| {
+            row![
+                text(kind.to_string()).size(12).width(100),
+                text(value).size(12),
+            ]
+                .spacing(5)
+                .into()
+        });
+    Column::with_children(rows).spacing(3).into()
+}
@@ -0,0 +1,504 @@
+use std::path::PathBuf;
+
+use iced::advanced::mouse;
+use iced::widget::{button, column, container, mouse_area, row, text, Row};
+use iced::{event, keyboard, window, Color, Element, Event, Length, Padding, Size, Task};
+
+use crate::fileops::Operation;
+use crate::session::{TabState, WindowState};
+use crate::settings::Settings;
+use crate::views::explorer::{self, Explorer};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    // Messages of the pane with the given id
+    Explorer(u64, explorer::Message),
+
+    NewTab,
+    CloseTab(u64),
+    CloseActiveTab,
+    CycleTab(bool),
+
+    DragStarted(u64),
+    Hovered(u64),
+    Unhovered(u64),
+    DragEnded,
+
+    ToggleSplit,
+    SwitchPane,
+
+    // Handled by the window around the tabs since they are shared between windows
+    // Copies or moves the selection of the focused pane into the other one
+    Transfer(Operation),
+    Copy,
+    Cut,
+    Paste,
+    NewWindow,
+    CloseWindow,
+}
+
+struct Pane {
+    id: u64,
+    explorer: Explorer,
+}
+
+struct Tab {
+    // Stays the same when tabs are reordered, unlike the index
+    id: u64,
+    // One pane, or two side by side in commander mode
+    panes: Vec<Pane>,
+    focused: usize,
+}
+
+impl Tab {
+    fn focused(&self) -> &Pane {
+        &self.panes[self.focused.min(self.panes.len() - 1)]
+    }
+}
+
+pub struct Tabs {
+    tabs: Vec<Tab>,
+    active: u64,
+    next_id: u64,
+    size: Option<Size>,
+
+    dragging: Option<u64>,
+    hovered: Option<u64>,
+
+    // Settings changed in one of the panes, for the other windows to pick up
+    changed_settings: Option<Settings>,
+}
+
+impl Tabs {
+    // Reopens the tabs of a saved window, or a single tab at the start folder if there are none
+    pub fn restore(state: WindowState) -> (Self, Task<Message>) {
+        let mut tabs = Self {
+            tabs: vec![],
+            active: 0,
+            next_id: 0,
+            size: None,
+            dragging: None,
+            hovered: None,
+            changed_settings: None,
+        };
+
+        let mut tasks = vec![];
+        for tab_state in state.tabs.into_iter().filter(|t| !t.panes.is_empty()) {
+            let focused = tab_state.focused.min(tab_state.panes.len() - 1);
+            let panes = tab_state.panes.into_iter().map(|pane| {
+                let (pane, task) = tabs.pane(Explorer::restore(pane));
+                tasks.push(task);
+                pane
+            }).collect();
+            let id = tabs.next_id();
+            tabs.tabs.push(Tab { id, panes, focused });
+        }
+
+        match tabs.tabs.get(state.active).or(tabs.tabs.last()) {
+            Some(tab) => tabs.active = tab.id,
+            None => tasks.push(tabs.push(Explorer::new())),
+        }
+        (tabs, Task::batch(tasks))
+    }
+
+    pub fn session(&self) -> WindowState {
+        WindowState {
+            size: self.size.map(|size| (size.width, size.height)),
+            tabs: self.tabs.iter().map(|tab| TabState {
+                panes: tab.panes.iter().map(|pane| pane.explorer.session()).collect(),
+                focused: tab.focused,
+            }).collect(),
+            active: self.index(self.active).unwrap_or(0),
+        }
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn pane(&mut self, (explorer, task): (Explorer, Task<explorer::Message>)) -> (Pane, Task<Message>) {
+        let id = self.next_id();
+        (Pane { id, explorer }, task.map(move |msg| Message::Explorer(id, msg)))
+    }
+
+    fn push(&mut self, explorer: (Explorer, Task<explorer::Message>)) -> Task<Message> {
+        let (pane, task) = self.pane(explorer);
+        let id = self.next_id();
+
+        // New tabs open right after the active one
+        let index = self.index(self.active).map(|i| i + 1).unwrap_or(self.tabs.len());
+        self.tabs.insert(index, Tab { id, panes: vec![pane], focused: 0 });
+        self.active = id;
+
+        task
+    }
+
+    fn index(&self, id: u64) -> Option<usize> {
+        self.tabs.iter().position(|t| t.id == id)
+    }
+
+    fn active_tab(&self) -> &Tab {
+        let index = self.index(self.active).unwrap_or(0);
+        &self.tabs[index]
+    }
+
+    fn active_tab_mut(&mut self) -> &mut Tab {
+        let index = self.index(self.active).unwrap_or(0);
+        &mut self.tabs[index]
+    }
+
+    fn find_pane(&mut self, id: u64) -> Option<(&mut Tab, usize)> {
+        self.tabs.iter_mut()
+            .find_map(|tab| tab.panes.iter().position(|p| p.id == id).map(|i| (tab, i)))
+    }
+
+    fn select(&mut self, id: u64) -> Task<Message> {
+        let Some(index) = self.index(id) else { return Task::none() };
+        self.active = id;
+        Task::batch(self.tabs[index].panes.iter().map(|pane| {
+            let id = pane.id;
+            pane.explorer.restore_scroll().map(move |msg| Message::Explorer(id, msg))
+        }))
+    }
+
+    fn close(&mut self, id: u64) -> Task<Message> {
+        let Some(index) = self.index(id) else { return Task::none() };
+        if self.tabs.len() == 1 {
+            return Task::done(Message::CloseWindow);
+        }
+        self.tabs.remove(index);
+        if self.active == id {
+            let next = self.tabs[index.min(self.tabs.len() - 1)].id;
+            return self.select(next);
+        }
+        Task::none()
+    }
+
+    // Tells every pane how much of the window it has, split panes get half each
+    fn layout(&mut self) -> Task<Message> {
+        let Some(size) = self.size else { return Task::none() };
+        Task::batch(self.tabs.iter_mut().flat_map(|tab| {
+            let width = size.width / tab.panes.len() as f32;
+            tab.panes.iter_mut().map(move |pane| {
+                let id = pane.id;
+                pane.explorer
+                    .update(explorer::Message::WindowResized(Size::new(width, size.height)))
+                    .map(move |msg| Message::Explorer(id, msg))
+            })
+        }).collect::<Vec<_>>())
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Explorer(id, explorer::Message::OpenInNewTab(data)) => {
+                let Some(tab) = self.tabs.iter().find(|t| t.panes.iter().any(|p| p.id == id)) else {
+                    return Task::none();
+                };
+                if !data.is_dir {
+                    return Task::none();
+                }
+                let current = tab.id;
+                let task = self.push(Explorer::at(data.path));
+                // Middle click keeps the current tab in front like in browsers
+                self.active = current;
+                Task::batch(vec![task, self.layout()])
+            }
+            Message::Explorer(_, explorer::Message::WindowResized(size)) => {
+                // Every pane lays itself out to the window, not only the visible ones
+                self.size = Some(size);
+                self.layout()
+            }
+            Message::Explorer(id, msg) => {
+                let Some((tab, index)) = self.find_pane(id) else { return Task::none() };
+                if is_interaction(&msg) {
+                    tab.focused = index;
+                }
+                let explorer = &mut tab.panes[index].explorer;
+                let task = explorer.update(msg).map(move |msg| Message::Explorer(id, msg));
+
+                if let Some(settings) = explorer.take_changed_settings() {
+                    for pane in self.tabs.iter_mut().flat_map(|t| t.panes.iter_mut()).filter(|p| p.id != id) {
+                        pane.explorer.set_settings(settings.clone());
+                    }
+                    self.changed_settings = Some(settings);
+                }
+                task
+            }
+            Message::NewTab => {
+                let task = self.push(Explorer::new());
+                Task::batch(vec![task, self.layout()])
+            }
+            Message::CloseTab(id) => self.close(id),
+            Message::CloseActiveTab => self.close(self.active),
+            Message::CycleTab(forward) => {
+                let index = self.index(self.active).unwrap_or(0);
+                let len = self.tabs.len();
+                let next = if forward { (index + 1) % len } else { (index + len - 1) % len };
+                self.select(self.tabs[next].id)
+            }
+            Message::DragStarted(id) => {
+                self.dragging = Some(id);
+                self.hovered = Some(id);
+                self.select(id)
+            }
+            Message::Hovered(id) => {
+                self.hovered = Some(id);
+                Task::none()
+            }
+            // Releasing outside of the tab bar leaves the tabs where they are
+            Message::Unhovered(id) => {
+                if self.hovered == Some(id) {
+                    self.hovered = None;
+                }
+                Task::none()
+            }
+            Message::DragEnded => {
+                if let (Some(from), Some(to)) = (self.dragging.take(), self.hovered) {
+                    if let (Some(from), Some(to)) = (self.index(from), self.index(to)) {
+                        let tab = self.tabs.remove(from);
+                        self.tabs.insert(to, tab);
+                    }
+                }
+                Task::none()
+            }
+            Message::ToggleSplit => {
+                let tab = self.active_tab();
+                let task = if tab.panes.len() > 1 {
+                    let tab = self.active_tab_mut();
+                    tab.panes.truncate(1);
+                    tab.focused = 0;
+                    Task::none()
+                } else {
+                    // The second pane starts where the first one is
+                    let path = tab.focused().explorer.current_path().clone();
+                    let (pane, task) = self.pane(Explorer::at(path));
+                    self.active_tab_mut().panes.push(pane);
+                    task
+                };
+                Task::batch(vec![task, self.layout()])
+            }
+            Message::SwitchPane => {
+                let tab = self.active_tab_mut();
+                tab.focused = (tab.focused + 1) % tab.panes.len();
+                Task::none()
+            }
+            Message::Transfer(_)
+            | Message::Copy
+            | Message::Cut
+            | Message::Paste
+            | Message::NewWindow
+            | Message::CloseWindow => Task::none(),
+        }
+    }
+
+    fn focused_explorer(&self) -> &Explorer {
+        &self.active_tab().focused().explorer
+    }
+
+    pub fn current_path(&self) -> PathBuf {
+        self.focused_explorer().current_path().clone()
+    }
+
+    pub fn selection(&self) -> Option<PathBuf> {
+        self.focused_explorer().selected()
+    }
+
+    // The selection of the focused pane and the directory of the other one
+    pub fn transfer(&self) -> Option<(PathBuf, PathBuf)> {
+        let tab = self.active_tab();
+        if tab.panes.len() < 2 {
+            return None;
+        }
+        let dest = tab.panes[(tab.focused + 1) % 2].explorer.current_path().clone();
+        Some((tab.focused().explorer.selected()?, dest))
+    }
+
+    // Reloads `dir` in every pane of every tab that shows it
+    pub fn reload(&mut self, dir: &PathBuf) -> Task<Message> {
+        Task::batch(self.tabs.iter_mut().flat_map(|t| t.panes.iter_mut()).map(|pane| {
+            let id = pane.id;
+            pane.explorer.reload(dir).map(move |msg| Message::Explorer(id, msg))
+        }).collect::<Vec<_>>())
+    }
+
+    pub fn take_changed_settings(&mut self) -> Option<Settings> {
+        self.changed_settings.take()
+    }
+
+    pub fn set_settings(&mut self, settings: Settings) {
+        for pane in self.tabs.iter_mut().flat_map(|t| t.panes.iter_mut()) {
+            pane.explorer.set_settings(settings.clone());
+        }
+    }
+
+    // Turns a window event into the messages it triggers, keys and clicks go to the focused pane
+    pub fn handle_event(&self, event: Event, status: event::Status) -> Vec<Message> {
+        let pane = self.active_tab().focused().id;
+        let split = self.active_tab().panes.len() > 1;
+        let mut messages = vec![];
+
+        match &event {
+            Event::Window(window::Event::Resized(size)) => {
+                messages.push(Message::Explorer(pane, explorer::Message::WindowResized(*size)));
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                messages.push(Message::DragEnded);
+            }
+            Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) if status == event::Status::Ignored => {
+                messages.extend(shortcut(key.clone(), *modifiers, pane));
+                if split {
+                    messages.extend(commander_shortcut(key.clone(), *modifiers));
+                }
+            }
+            _ => {}
+        }
+
+        // Same as `event::listen`, the explorer only sees what no widget handled
+        if status == event::Status::Ignored {
+            messages.push(Message::Explorer(pane, explorer::Message::EventOccurred(event)));
+        }
+        messages
+    }
+
+    pub fn view(&self) -> Element<Message> {
+        let tab = self.active_tab();
+        let split = tab.panes.len() > 1;
+
+        let panes = Row::with_children(tab.panes.iter().enumerate().map(|(i, pane)| {
+            let id = pane.id;
+            let focused = split && i == tab.focused;
+            container(pane.explorer.view().map(move |msg| Message::Explorer(id, msg)))
+                .style(if focused {
+                    |theme: &iced::Theme| container::Style {
+                        border: iced::Border {
+                            color: theme.palette().primary,
+                            width: 1.0,
+                            radius: 0.0.into(),
+                        },
+                        ..Default::default()
+                    }
+                } else {
+                    |_: &_| container::Style::default()
+                })
+                .into()
+        }));
+
+        column![
+            self.tabbar(),
+            panes,
+        ]
+            .into()
+    }
+
+    fn tabbar(&self) -> Element<Message> {
+        let mut bar = Row::new()
+            .spacing(2)
+            .padding(Padding::new(0.0).top(5).left(10).right(10));
+
+        for tab in &self.tabs {
+            let id = tab.id;
+            let label = tab_label(tab.focused().explorer.current_path());
+            let active = id == self.active;
+
+            bar = bar.push(
+                mouse_area(
+                    container(
+                        row![
+                            text(label).size(14).width(Length::Fill),
+                            button(text("×").size(14))
+                                .on_press(Message::CloseTab(id))
+                                .padding(0)
+                                .style(button::text),
+                        ]
+                            .spacing(5)
+                    )
+                        .padding(Padding::new(4.0).left(10).right(6))
+                        .width(160)
+                        .style(if active {
+                            |_: &_| container::Style {
+                                background: Some(Color::from_rgba(1.0, 1.0, 1.0, 0.08).into()),
+                                ..Default::default()
+                            }
+                        } else {
+                            |_: &_| container::Style {
+                                background: Some(Color::from_rgba(1.0, 1.0, 1.0, 0.02).into()),
+                                ..Default::default()
+                            }
+                        })
+                )
+                    .on_press(Message::DragStarted(id))
+                    .on_middle_press(Message::CloseTab(id))
+                    .on_enter(Message::Hovered(id))
+                    .on_exit(Message::Unhovered(id))
+                    .interaction(mouse::Interaction::Pointer)
+            );
+        }
+
+        bar
+            .push(
+                button(text("+").size(14))
+                    .on_press(Message::NewTab)
+                    .padding(Padding::new(4.0).left(8).right(8))
+                    .style(button::text)
+            )
+            .push(
+                button(text(if self.active_tab().panes.len() > 1 { "Single pane" } else { "Split" }).size(14))
+                    .on_press(Message::ToggleSplit)
+                    .padding(Padding::new(4.0).left(8).right(8))
+                    .style(button::text)
+            )
+            .into()
+    }
+
+    pub fn title(&self) -> String {
+        self.focused_explorer().title()
+    }
+}
+
+// Whether the message comes from the user working in a pane, which focuses it
+fn is_interaction(message: &explorer::Message) -> bool {
+    matches!(
+        message,
+        explorer::Message::SelectFile(_)
+            | explorer::Message::OpenFile(_)
+            | explorer::Message::History(_)
+            | explorer::Message::ClickedOn(_)
+            | explorer::Message::SortTree(_)
+            | explorer::Message::MillerSelect(..)
+            | explorer::Message::ToggleExpanded(_)
+    )
+}
+
+fn shortcut(key: keyboard::Key, modifiers: keyboard::Modifiers, pane: u64) -> Option<Message> {
+    match key.as_ref() {
+        keyboard::Key::Named(keyboard::key::Named::Tab) if modifiers.control() => Some(Message::CycleTab(!modifiers.shift())),
+        keyboard::Key::Character("t") if modifiers.command() => Some(Message::NewTab),
+        keyboard::Key::Character("w") if modifiers.command() => Some(Message::CloseActiveTab),
+        keyboard::Key::Character("n") if modifiers.command() => Some(Message::NewWindow),
+        keyboard::Key::Character("c") if modifiers.command() => Some(Message::Copy),
+        keyboard::Key::Character("x") if modifiers.command() => Some(Message::Cut),
+        keyboard::Key::Character("v") if modifiers.command() => Some(Message::Paste),
+        keyboard::Key::Character("d") if modifiers.command() => Some(Message::Explorer(pane, explorer::Message::ToggleBookmark)),
+        _ => None,
+    }
+}
+
+// Midnight Commander style keys, only active while the tab is split
+fn commander_shortcut(key: keyboard::Key, modifiers: keyboard::Modifiers) -> Option<Message> {
+    if !modifiers.is_empty() {
+        return None;
+    }
+    match key.as_ref() {
+        keyboard::Key::Named(keyboard::key::Named::Tab) => Some(Message::SwitchPane),
+        keyboard::Key::Named(keyboard::key::Named::F5) => Some(Message::Transfer(Operation::Copy)),
+        keyboard::Key::Named(keyboard::key::Named::F6) => Some(Message::Transfer(Operation::Move)),
+        _ => None,
+    }
+}
+
+fn tab_label(path: &PathBuf) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or(path.to_string_lossy().to_string())
+}
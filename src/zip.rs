@@ -0,0 +1,161 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use flate2::read::DeflateDecoder;
+
+use crate::utils::read_at;
+
+// Entries bigger than this aren't extracted, metadata files are a few KB
+const MAX_ENTRY_SIZE: u64 = 4 * 1024 * 1024;
+
+// Archives with a bigger central directory aren't opened
+const MAX_DIRECTORY_SIZE: u64 = 16 * 1024 * 1024;
+
+struct Entry {
+    name: Vec<u8>,
+    method: u16,
+    compressed: u64,
+    size: u64,
+    // Offset of the local header
+    local: u64,
+}
+
+// A zip archive of which only the central directory is read up front
+pub struct Archive<R> {
+    reader: R,
+    entries: Vec<Entry>,
+}
+
+impl<R: Read + Seek> Archive<R> {
+    pub fn open(mut reader: R) -> Option<Self> {
+        let len = reader.seek(SeekFrom::End(0)).ok()?;
+        // The end of central directory record is followed by a comment of at most 64 KB
+        let tail = read_at(&mut reader, len.saturating_sub(22 + 65535), 22 + 65535)?;
+        let end = (0..tail.len().saturating_sub(21)).rev().find(|&i| tail[i..].starts_with(b"PK\x05\x06"))?;
+        let count = u16_at(&tail, end + 10)? as usize;
+        let size = u32_at(&tail, end + 12)? as u64;
+        let offset = u32_at(&tail, end + 16)? as u64;
+        if size > MAX_DIRECTORY_SIZE {
+            return None;
+        }
+
+        let directory = read_at(&mut reader, offset, size)?;
+        let mut entries = vec![];
+        let mut position = 0;
+        for _ in 0..count {
+            if !directory.get(position..)?.starts_with(b"PK\x01\x02") {
+                return None;
+            }
+            let name_len = u16_at(&directory, position + 28)? as usize;
+            let extra_len = u16_at(&directory, position + 30)? as usize;
+            let comment_len = u16_at(&directory, position + 32)? as usize;
+            entries.push(Entry {
+                name: directory.get(position + 46..position + 46 + name_len)?.to_vec(),
+                method: u16_at(&directory, position + 10)?,
+                compressed: u32_at(&directory, position + 20)? as u64,
+                size: u32_at(&directory, position + 24)? as u64,
+                local: u32_at(&directory, position + 42)? as u64,
+            });
+            position += 46 + name_len + extra_len + comment_len;
+        }
+        Some(Self { reader, entries })
+    }
+
+    // Extracts one file by its name, stored and deflated entries are supported
+    pub fn entry(&mut self, name: &str) -> Option<Vec<u8>> {
+        let entry = self.entries.iter().find(|entry| entry.name == name.as_bytes())?;
+        if entry.size > MAX_ENTRY_SIZE {
+            return None;
+        }
+        // The local header has its own name and extra field lengths
+        let header = read_at(&mut self.reader, entry.local, 30)?;
+        let start = entry.local + 30 + u16_at(&header, 26)? as u64 + u16_at(&header, 28)? as u64;
+        match entry.method {
+            0 => read_at(&mut self.reader, start, entry.size),
+            8 => {
+                self.reader.seek(SeekFrom::Start(start)).ok()?;
+                let mut out = vec![];
+                DeflateDecoder::new((&mut self.reader).take(entry.compressed))
+                    .take(MAX_ENTRY_SIZE)
+                    .read_to_end(&mut out)
+                    .ok()?;
+                Some(out)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    // Local headers and data, then the central directory and its end record
+    fn archive(files: &[(&str, &[u8], bool)]) -> Vec<u8> {
+        let mut data = vec![];
+        let mut directory = vec![];
+        for (name, contents, deflate) in files {
+            let stored = if *deflate {
+                let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+                encoder.write_all(contents).unwrap();
+                encoder.finish().unwrap()
+            } else {
+                contents.to_vec()
+            };
+            let method: u16 = if *deflate { 8 } else { 0 };
+            let sizes = [(stored.len() as u32).to_le_bytes(), (contents.len() as u32).to_le_bytes()].concat();
+            let offset = data.len() as u32;
+            data.extend([&b"PK\x03\x04"[..], &[0; 4], &method.to_le_bytes(), &[0; 8], &sizes, &(name.len() as u16).to_le_bytes(), &[0; 2], name.as_bytes(), &stored].concat());
+            directory.extend([&b"PK\x01\x02"[..], &[0; 6], &method.to_le_bytes(), &[0; 8], &sizes, &(name.len() as u16).to_le_bytes(), &[0; 12], &offset.to_le_bytes(), name.as_bytes()].concat());
+        }
+        let end = [&b"PK\x05\x06"[..], &[0; 6], &(files.len() as u16).to_le_bytes(), &(directory.len() as u32).to_le_bytes(), &(data.len() as u32).to_le_bytes(), &[0; 2]].concat();
+        [data, directory, end].concat()
+    }
+
+    #[test]
+    fn extracts_stored_and_deflated_entries() {
+        let file = archive(&[("mimetype", b"application/epub+zip", false), ("meta.xml", b"<meta/>", true)]);
+        let mut zip = Archive::open(Cursor::new(file)).unwrap();
+        assert_eq!(zip.entry("mimetype"), Some(b"application/epub+zip".to_vec()));
+        assert_eq!(zip.entry("meta.xml"), Some(b"<meta/>".to_vec()));
+        assert_eq!(zip.entry("missing"), None);
+    }
+
+    #[test]
+    fn broken_archives_do_not_panic() {
+        let file = archive(&[("a", b"first", false), ("b", b"second", true)]);
+        for len in 0..file.len() {
+            if let Some(mut zip) = Archive::open(Cursor::new(&file[..len])) {
+                zip.entry("a");
+                zip.entry("b");
+            }
+        }
+        assert!(Archive::open(Cursor::new(b"PK\x05\x06")).is_none());
+        // More entries than the directory holds
+        let mut file = archive(&[("a", b"first", false)]);
+        let count = file.len() - 12;
+        file[count] = 2;
+        assert!(Archive::open(Cursor::new(file)).is_none());
+    }
+
+    #[test]
+    fn big_entries_are_not_extracted() {
+        let mut file = archive(&[("big", b"small", false)]);
+        // The size in the central directory, after the local header and data of the entry
+        let size = 30 + 3 + 5 + 24;
+        file[size..size + 4].copy_from_slice(&(MAX_ENTRY_SIZE as u32 + 1).to_le_bytes());
+        assert_eq!(Archive::open(Cursor::new(file)).unwrap().entry("big"), None);
+    }
+}